@@ -1,17 +1,23 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
 use std::usize::MAX;
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use cairo_lang_runner::short_string::as_cairo_short_string;
-use cairo_lang_runner::{RunResultStarknet, RunResultValue, SierraCasmRunner, StarknetState};
-use cairo_lang_sierra::ids::FunctionId;
+use cairo_lang_runner::{Arg, RunResultStarknet, RunResultValue};
 use cairo_lang_sierra::program::{Function, ProgramArtifact, VersionedProgram};
 use camino::Utf8PathBuf;
 use clap::Parser;
 use indoc::formatdoc;
-use serde::Serializer;
-use pyo3::types::PyAny;
+use log::debug;
+use serde::{Serialize, Serializer};
+use starknet_types_core::felt::Felt as Felt252;
+use num_bigint::BigUint;
+use pyo3::exceptions::PyException;
+use pyo3::types::{PyAny, PyBool, PyDict, PyInt, PyList, PyString, PyTuple};
 use pyo3::prelude::*;
 use pyo3::wrap_pyfunction;
 
@@ -22,11 +28,23 @@ use scarb_ui::args::{PackagesFilter, VerbositySpec};
 use scarb_ui::components::Status;
 use scarb_ui::{Message, OutputFormat, Ui};
 use std::time::Instant;
+use test_runner::context::ExecutionContext;
+use test_runner::contract::{ContractClass, ContractEnv};
+use test_runner::corpus::FailureCorpus;
+use test_runner::coverage::CoverageReport;
+use test_runner::differential::{render_output, DifferentialConfig, DifferentialMismatch};
+use test_runner::events::{Event, EventNotEmitted, L2ToL1Message};
+use test_runner::gas::{GasReport, GasSnapshot};
+use test_runner::profile::ProfileReport;
+use test_runner::program::{load_artifact, CairoProgram, CairoRun, Instrumentation};
+use test_runner::resources::ExecutionResources;
+use test_runner::snapshot::{self, SnapshotMismatch, SnapshotUpdate};
+use test_runner::strategy::StrategyConfig;
+use test_runner::panic::CairoPanic;
+use test_runner::value::CairoValue;
 
 
-mod deserialization;
 
-const EXECUTABLE_NAME: &str = "main";
 const DEFAULT_MAIN_FUNCTION: &str = "::main";
 
 /// Execute the main function of a package.
@@ -49,75 +67,407 @@ pub struct Args {
     #[arg(long, default_value_t = false)]
     print_full_memory: bool,
 
+    /// Print the VM resources used by the run: steps, memory holes and builtin usage.
+    #[arg(long, default_value_t = false)]
+    print_resources: bool,
+
+    /// Print what the function prints as it runs, instead of with the run summary.
+    #[arg(long, default_value_t = false)]
+    stream_output: bool,
+
+    /// Write the Cairo source lines executed by the run to this LCOV file.
+    ///
+    /// Requires `unstable-add-statements-code-locations-debug-info = true` in the `[cairo]`
+    /// section of Scarb.toml.
+    #[arg(long)]
+    coverage: Option<Utf8PathBuf>,
+
+    /// Write the VM steps executed per call stack to this file, in the folded stacks format read
+    /// by flamegraph tools such as `inferno-flamegraph`.
+    #[arg(long)]
+    profile: Option<Utf8PathBuf>,
+
+    /// Number of most expensive functions listed after a profiled run.
+    #[arg(long, default_value_t = 10, requires = "profile")]
+    profile_top: usize,
+
+    /// Call the functions of the failing cases stored in this failure corpus file with their
+    /// recorded arguments, and compare their outputs with the recorded ones, instead of running
+    /// the selected function. Fails if any of the cases still reproduces.
+    #[arg(long)]
+    replay_corpus: Option<Utf8PathBuf>,
+
     /// Do not rebuild the package.
     #[arg(long, default_value_t = false)]
     no_build: bool,
 
+    /// Print the run summary as JSON.
+    #[arg(long, default_value_t = false)]
+    json: bool,
+
     /// Logging verbosity.
     #[command(flatten)]
     pub verbose: VerbositySpec,
 
     /// Program arguments.
     ///
-    /// This should be a JSON array of numbers, decimal bigints or recursive arrays of those. For example, pass `[1]`
-    /// to the following function `fn main(a: u64)`, or pass `[1, "2"]` to `fn main(a: u64, b: u64)`,
-    /// or `[1, 2, [3, 4, 5]]` to `fn main(t: (u64, u64), v: Array<u64>)`.
+    /// This should be a JSON array with one element per parameter of the function, encoded according
+    /// to its type. For example, pass `[1]` to the following function `fn main(a: u64)`, or pass
+    /// `[1, "2"]` to `fn main(a: u64, b: u256)`, or `[[1, 2], [3, 4, 5]]` to
+    /// `fn main(t: (u64, u64), v: Array<u64>)`. Structs are passed as arrays of their fields.
     #[arg(default_value = "[]")]
-    arguments: deserialization::Args,
+    arguments: String,
 
     /// Path to the JSON file containing program arguments.
     ///
     /// It specified, `[ARGUMENTS]` CLI parameter will be ignored.
     #[arg(long)]
     arguments_file: Option<Utf8PathBuf>,
+
+    /// Block number of the execution info read by the function.
+    #[arg(long)]
+    block_number: Option<u64>,
+
+    /// Block timestamp of the execution info read by the function.
+    #[arg(long)]
+    block_timestamp: Option<u64>,
+
+    /// Sequencer address of the execution info read by the function. Addresses, hashes and
+    /// storage values are felts, in decimal or `0x`-prefixed hexadecimal.
+    #[arg(long)]
+    sequencer_address: Option<Felt252>,
+
+    /// Caller address of the execution info read by the function.
+    #[arg(long)]
+    caller_address: Option<Felt252>,
+
+    /// Contract address of the execution info read by the function, whose storage it accesses.
+    #[arg(long)]
+    contract_address: Option<Felt252>,
+
+    /// Transaction hash of the execution info read by the function.
+    #[arg(long)]
+    transaction_hash: Option<Felt252>,
+
+    /// Chain ID of the execution info read by the function.
+    #[arg(long)]
+    chain_id: Option<Felt252>,
+
+    /// Initial storage value, as `CONTRACT:KEY=VALUE`. Can be repeated.
+    #[arg(long, value_name = "CONTRACT:KEY=VALUE", value_parser = parse_storage_value)]
+    storage: Vec<(Felt252, Felt252, Felt252)>,
+
+    /// Path to the gas snapshot file to compare the gas consumed by the run against.
+    #[arg(long)]
+    gas_snapshot: Option<Utf8PathBuf>,
+
+    /// Record the gas consumed by the run in the gas snapshot file instead of comparing it.
+    #[arg(long, default_value_t = false, requires = "gas_snapshot")]
+    update_gas_snapshot: bool,
+
+    /// Gas increase tolerated by the snapshot comparison, in percent of the recorded gas.
+    #[arg(long, default_value_t = 0.0)]
+    gas_threshold: f64,
+
+    /// Only warn about gas increases beyond the threshold instead of failing.
+    #[arg(long, default_value_t = false)]
+    gas_warn_only: bool,
 }
 
-pub fn run_scarb_pytest(args: Args) -> Result<String> {
-    let ui = Ui::new(args.verbose.clone().into(), OutputFormat::Text);
-    let result = inner_runner(&ui, args)?;
-    let result_string = match result.value {
-        RunResultValue::Success(values) => {
-            let values = values
-                .into_iter()
-                .map(|v| v.to_string())
-                .collect::<Vec<_>>();
-            let values = values.join(", ");
-            format!("Run completed successfully, returning [{values}]")
+/// The result of running a Cairo function.
+#[pyclass(name = "RunResult", module = "scarb_pytest", frozen, get_all)]
+struct PyRunResult {
+    /// The felts returned by the function, empty if it panicked.
+    values: Vec<BigUint>,
+    panicked: bool,
+    /// The panic data, empty if the function didn't panic.
+    panic_data: Vec<BigUint>,
+    /// The gas left after the run, if gas metering is enabled.
+    gas_remaining: Option<BigUint>,
+    /// The gas consumed by the run, if gas metering is enabled.
+    gas_consumed: Option<u64>,
+    /// The number of Cairo VM steps of the run.
+    steps: usize,
+    /// The VM resources used by the run.
+    resources: Py<PyExecutionResources>,
+    /// The returned value decoded according to the function's return type, as returned by
+    /// `Program.call_value`. `None` if the function panicked or its return value couldn't be
+    /// decoded.
+    value: Option<PyObject>,
+    /// What the function printed with `print!`, `println!` or `core::debug::print`.
+    output: String,
+    /// The events emitted by the run, in order.
+    events: Vec<Py<PyEvent>>,
+    /// The messages to L1 sent by the run, in order.
+    l2_to_l1_messages: Vec<Py<PyL2ToL1Message>>,
+}
+
+#[pymethods]
+impl PyRunResult {
+    fn __repr__(&self) -> String {
+        let felts = |values: &[BigUint]| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if self.panicked {
+            format!("RunResult(panicked, panic_data=[{}])", felts(&self.panic_data))
+        } else {
+            format!("RunResult(values=[{}])", felts(&self.values))
         }
-        RunResultValue::Panic(values) => {
-            let mut formatter = String::from("Run panicked with [");
-            for (i, value) in values.iter().enumerate() {
-                if i > 0 {
-                    formatter.push_str(", ");
-                }
-                match as_cairo_short_string(value) {
-                    Some(as_string) => formatter.push_str(&format!("{value} ('{as_string}')")),
-                    None => formatter.push_str(&value.to_string()),
+    }
+}
+
+impl PyRunResult {
+    /// Builds the result of running `function`, or fails with a `CairoPanic` if it panicked and
+    /// `raise_on_panic` is set.
+    fn new(
+        program: &CairoProgram,
+        function: &Function,
+        run: CairoRun,
+        raise_on_panic: bool,
+        py: Python<'_>,
+    ) -> PyResult<Self> {
+        let gas_consumed = program.gas_consumed(&run.result);
+        Self::from_run(run, gas_consumed, raise_on_panic, py, |values, memory| {
+            let value = program.decode_return_value(function, values, memory).ok();
+            (values.to_vec(), value)
+        })
+    }
+
+    /// Same as `PyRunResult::new`, with the values and decoded value of a successful run given by
+    /// `decode` from its returned felts and memory.
+    fn from_run(
+        run: CairoRun,
+        gas_consumed: Option<u64>,
+        raise_on_panic: bool,
+        py: Python<'_>,
+        decode: impl FnOnce(&[Felt252], &[Option<Felt252>]) -> (Vec<Felt252>, Option<CairoValue>),
+    ) -> PyResult<Self> {
+        let CairoRun {
+            result,
+            output,
+            events,
+            l2_to_l1_messages,
+        } = run;
+        let to_ints = |felts: &[Felt252]| felts.iter().map(|felt| felt.to_biguint()).collect();
+        let gas_remaining = result.gas_counter.map(|gas| gas.to_biguint());
+        let steps = result.used_resources.basic_resources.n_steps;
+        let resources = Py::new(
+            py,
+            PyExecutionResources::from(ExecutionResources::from(&result.used_resources)),
+        )?;
+        let events = events
+            .into_iter()
+            .map(|event| Py::new(py, PyEvent::from(event)))
+            .collect::<PyResult<_>>()?;
+        let l2_to_l1_messages = l2_to_l1_messages
+            .into_iter()
+            .map(|message| Py::new(py, PyL2ToL1Message::from(message)))
+            .collect::<PyResult<_>>()?;
+
+        Ok(match &result.value {
+            RunResultValue::Success(values) => {
+                let (values, value) = decode(values, &result.memory);
+                Self {
+                    values: to_ints(&values),
+                    panicked: false,
+                    panic_data: vec![],
+                    gas_remaining,
+                    gas_consumed,
+                    steps,
+                    resources,
+                    value: value
+                        .map(|value| cairo_value_to_py(value, py))
+                        .transpose()?,
+                    output,
+                    events,
+                    l2_to_l1_messages,
                 }
             }
-            formatter.push_str("]");
-            formatter
+            RunResultValue::Panic(panic_data) if raise_on_panic => {
+                let panic = CairoPanic::new(panic_data.clone()).with_output(output);
+                return Err(to_py_err(panic.into(), py));
+            }
+            RunResultValue::Panic(panic_data) => Self {
+                values: vec![],
+                panicked: true,
+                panic_data: to_ints(panic_data),
+                gas_remaining,
+                gas_consumed,
+                steps,
+                resources,
+                value: None,
+                output,
+                events,
+                l2_to_l1_messages,
+            },
+        })
+    }
+}
+
+/// An event emitted by a run.
+#[pyclass(name = "Event", module = "scarb_pytest", frozen)]
+struct PyEvent {
+    /// The address of the contract the event was emitted from.
+    #[pyo3(get)]
+    from_address: BigUint,
+    #[pyo3(get)]
+    keys: Vec<BigUint>,
+    #[pyo3(get)]
+    data: Vec<BigUint>,
+    event: Event,
+}
+
+#[pymethods]
+impl PyEvent {
+    fn __repr__(&self) -> String {
+        format!(
+            "Event(from_address={:#x}, keys={:?}, data={:?})",
+            self.from_address, self.keys, self.data
+        )
+    }
+}
+
+impl From<Event> for PyEvent {
+    fn from(event: Event) -> Self {
+        let to_ints = |felts: &[Felt252]| felts.iter().map(|felt| felt.to_biguint()).collect();
+        Self {
+            from_address: event.from_address.to_biguint(),
+            keys: to_ints(&event.keys),
+            data: to_ints(&event.data),
+            event,
+        }
+    }
+}
+
+/// A message to L1 sent by a run.
+#[pyclass(name = "L2ToL1Message", module = "scarb_pytest", frozen, get_all)]
+struct PyL2ToL1Message {
+    /// The address of the contract the message was sent from.
+    from_address: BigUint,
+    /// The L1 address the message is sent to.
+    to_address: BigUint,
+    payload: Vec<BigUint>,
+}
+
+#[pymethods]
+impl PyL2ToL1Message {
+    fn __repr__(&self) -> String {
+        format!(
+            "L2ToL1Message(from_address={:#x}, to_address={:#x}, payload={:?})",
+            self.from_address, self.to_address, self.payload
+        )
+    }
+}
+
+impl From<L2ToL1Message> for PyL2ToL1Message {
+    fn from(message: L2ToL1Message) -> Self {
+        Self {
+            from_address: message.from_address.to_biguint(),
+            to_address: message.to_address.to_biguint(),
+            payload: message.payload.iter().map(|felt| felt.to_biguint()).collect(),
+        }
+    }
+}
+
+/// Raised when a Cairo function panics.
+#[pyclass(extends = PyException, name = "CairoPanic", module = "scarb_pytest")]
+struct PyCairoPanic {
+    /// The raw panic data.
+    #[pyo3(get)]
+    felts: Vec<BigUint>,
+    /// Each panic data felt decoded as a short string, or `None`.
+    #[pyo3(get)]
+    short_strings: Vec<Option<String>>,
+    /// The `ByteArray` panic message, if any.
+    #[pyo3(get)]
+    message: Option<String>,
+    /// What the function printed before panicking.
+    #[pyo3(get)]
+    output: String,
+    reason: String,
+}
+
+#[pymethods]
+impl PyCairoPanic {
+    fn __str__(&self) -> String {
+        if self.output.is_empty() {
+            return self.reason.clone();
         }
+        format!("{}\n\nCairo output:\n{}", self.reason, self.output.trim_end())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CairoPanic({:?})", self.reason)
+    }
+}
+
+/// Converts an error to a Python exception: `CairoPanic` for panics, `RuntimeError` otherwise.
+fn to_py_err(error: anyhow::Error, py: Python<'_>) -> PyErr {
+    if let Some(mismatch) = error.downcast_ref::<SnapshotMismatch>() {
+        return PyErr::new::<pyo3::exceptions::PyAssertionError, _>(mismatch.to_string());
+    }
+    if let Some(mismatch) = error.downcast_ref::<DifferentialMismatch>() {
+        return PyErr::new::<pyo3::exceptions::PyAssertionError, _>(mismatch.to_string());
+    }
+    if let Some(not_emitted) = error.downcast_ref::<EventNotEmitted>() {
+        return PyErr::new::<pyo3::exceptions::PyAssertionError, _>(not_emitted.to_string());
+    }
+    let Some(panic) = error.downcast_ref::<CairoPanic>() else {
+        return PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("{:#}", error));
     };
-    Ok(result_string)
+
+    let panic = PyCairoPanic {
+        felts: panic.felts.iter().map(|felt| felt.to_biguint()).collect(),
+        short_strings: panic.short_strings.clone(),
+        message: panic.message.clone(),
+        output: panic.output.clone(),
+        reason: panic.reason(),
+    };
+    match Bound::new(py, panic) {
+        Ok(panic) => PyErr::from_value_bound(panic.into_any()),
+        Err(e) => e,
+    }
+}
+
+/// Returns the pending snapshots under `dir`, written by mismatching `Program.assert_snapshot`
+/// calls.
+#[pyfunction]
+fn pending_snapshots(dir: PathBuf, py: Python<'_>) -> PyResult<Vec<PathBuf>> {
+    snapshot::pending_snapshots(&dir).map_err(|e| to_py_err(e, py))
 }
 
+/// Replaces the snapshots under `dir` with their pending versions, and returns the updated
+/// snapshot files.
+#[pyfunction]
+fn accept_snapshots(dir: PathBuf, py: Python<'_>) -> PyResult<Vec<PathBuf>> {
+    snapshot::accept_snapshots(&dir).map_err(|e| to_py_err(e, py))
+}
+
+/// Deletes the pending snapshots under `dir`, and returns the deleted files.
+#[pyfunction]
+fn reject_snapshots(dir: PathBuf, py: Python<'_>) -> PyResult<Vec<PathBuf>> {
+    snapshot::reject_snapshots(&dir).map_err(|e| to_py_err(e, py))
+}
 
 #[pyfunction]
-#[pyo3(signature = (package=None, function=None, arguments=None))]
+#[pyo3(signature = (package=None, function=None, arguments=None, *, raise_on_panic=true))]
 fn run_scarb_pytest_py(
     package: Option<String>,
     function: Option<String>,
     arguments: Option<PyObject>,
+    raise_on_panic: bool,
     py: Python<'_>,
-) -> PyResult<String> {
-    println!("Debug: Executing scarb_pytest_py with package: {:?}, function: {:?}, arguments: {:?}", package, function, arguments);
+) -> PyResult<PyRunResult> {
+    debug!("Executing scarb_pytest_py with package: {:?}, function: {:?}, arguments: {:?}", package, function, arguments);
     let start = Instant::now();
 
-    println!("extra log");
     let json_args = match arguments {
         Some(arguments) => python_to_json(arguments, py)?,
-        None => "".to_string(),
+        None => "[]".to_string(),
     };
 
     let function = if let Some(function) = function {
@@ -127,7 +477,7 @@ fn run_scarb_pytest_py(
     };
 
     let package_str = if let Some(package) = package {
-        println!("Debug: Setting SCARB_PACKAGES_FILTER to {:?}", package);
+        debug!("Setting SCARB_PACKAGES_FILTER to {:?}", package);
         package
     } else {
         "*".to_string()
@@ -136,30 +486,942 @@ fn run_scarb_pytest_py(
     let json_args_string = json_args.as_str();
     let invoke_commands = vec!["scarb-cairo-run", "-p", package_str.as_str(), "--function", function.as_str(), json_args_string, "--no-build"];
     // ArgsOs { inner: ["/Users/msaug/deps/cairo-lint/target/release/scarb-cairo-lint", "-p", "evm", "-t", "--fix"] }
-    println!("Debug: Invoking scarb-cairo-run with args: {:?}", invoke_commands);
+    debug!("Invoking scarb-cairo-run with args: {:?}", invoke_commands);
     // let packages_filter = PackagesFilter::parse_from(invoke_commands.into_iter());
     // println!("Debug: Packages filter: {:?}", packages_filter);
 
     let args = Args::parse_from(invoke_commands.into_iter());
 
-    println!("Debug: Running scarb_pytest with args: {:?}", args);
-    println!("Debug: Time taken to parse args: {:?}", start.elapsed());
-    run_scarb_pytest(args).map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+    debug!("Running scarb_pytest with args: {:?}", args);
+    debug!("Time taken to parse args: {:?}", start.elapsed());
+    let ui = Ui::new(args.verbose.clone().into(), OutputFormat::Text);
+    let program = load_program(&ui, &args).map_err(|e| to_py_err(e, py))?;
+    let run = run_program(&program, &args).map_err(|e| to_py_err(e, py))?;
+    let function = program
+        .function(args.function.as_deref())
+        .map_err(|e| to_py_err(e, py))?;
+    PyRunResult::new(&program, function, run, raise_on_panic, py)
+}
+
+/// A Cairo program compiled once, whose functions can be called any number of times.
+///
+/// Construct it once per test session (e.g. in a pytest fixture) from either a Scarb package name
+/// or the path of a Sierra program JSON file.
+#[pyclass(name = "Program", module = "scarb_pytest")]
+struct PyProgram {
+    program: CairoProgram,
+    /// The gas consumed by the calls made so far.
+    gas: Mutex<GasSnapshot>,
+}
+
+impl PyProgram {
+    fn record_gas(&self, function: &Function, args: &[Arg], result: &RunResultStarknet) {
+        if let Some(gas) = self.program.gas_consumed(result) {
+            self.gas
+                .lock()
+                .expect("gas snapshot lock poisoned")
+                .record(&function.id.to_string(), args, gas);
+        }
+    }
+}
+
+#[pymethods]
+impl PyProgram {
+    /// With `coverage`, the Cairo source lines executed by the calls are collected for
+    /// `coverage_report`. With `profile`, the VM steps executed per call stack are collected for
+    /// `profile_report`. The calls run in `context`, unless given their own. What the calls print
+    /// is returned in their `output`, and with `stream_output` also written to stdout as they
+    /// print it.
+    #[new]
+    #[pyo3(signature = (package=None, sierra_path=None, available_gas=None, *, coverage=false, profile=false, context=None, stream_output=false))]
+    fn new(
+        package: Option<String>,
+        sierra_path: Option<PathBuf>,
+        available_gas: Option<usize>,
+        coverage: bool,
+        profile: bool,
+        context: Option<PyExecutionContext>,
+        stream_output: bool,
+    ) -> PyResult<Self> {
+        let program = match (package, sierra_path) {
+            (_, Some(sierra_path)) => load_artifact(&sierra_path)
+                .map(|artifact| (artifact, GasLimit::parse(available_gas))),
+            (package, None) => {
+                let package = package.unwrap_or_else(|| "*".to_string());
+                let available_gas = available_gas.map(|gas| gas.to_string());
+                let mut invoke_commands = vec!["scarb-cairo-run", "-p", package.as_str(), "--no-build"];
+                if let Some(available_gas) = available_gas.as_deref() {
+                    invoke_commands.extend(["--available-gas", available_gas]);
+                }
+                let args = Args::parse_from(invoke_commands);
+                let ui = Ui::new(args.verbose.clone().into(), OutputFormat::Text);
+                load_sierra_program(&ui, &args)
+            }
+        }
+        .and_then(|(artifact, available_gas)| {
+            let instrumentation = Instrumentation { coverage, profile };
+            CairoProgram::instrumented(artifact, available_gas.value(), instrumentation)
+        })
+        .map(|program| match context {
+            Some(context) => program.with_context(context.context),
+            None => program,
+        })
+        .map(|program| program.with_streamed_output(stream_output))
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("{:#}", e)))?;
+
+        Ok(Self {
+            program,
+            gas: Default::default(),
+        })
+    }
+
+    /// Returns the Cairo source lines executed by the calls made so far, keeping only the files
+    /// under `source_root` if given. Requires the program to be built with `coverage=True`.
+    #[pyo3(signature = (source_root=None))]
+    fn coverage_report(
+        &self,
+        source_root: Option<PathBuf>,
+        py: Python<'_>,
+    ) -> PyResult<PyCoverageReport> {
+        let mut report = self
+            .program
+            .coverage_report()
+            .map_err(|e| to_py_err(e, py))?;
+        if let Some(source_root) = source_root {
+            report.retain_under(&source_root);
+        }
+        Ok(PyCoverageReport { report })
+    }
+
+    /// Returns the VM steps executed per call stack by the calls made so far. Requires the
+    /// program to be built with `profile=True`.
+    fn profile_report(&self, py: Python<'_>) -> PyResult<PyProfileReport> {
+        let report = self
+            .program
+            .profile_report()
+            .map_err(|e| to_py_err(e, py))?;
+        Ok(PyProfileReport { report })
+    }
+
+    /// Returns the gas consumed by every call made so far through this program, by function and
+    /// argument set.
+    fn gas_snapshot(&self) -> PyGasSnapshot {
+        PyGasSnapshot {
+            snapshot: self.gas.lock().expect("gas snapshot lock poisoned").clone(),
+        }
+    }
+
+    /// Returns `(name, n_params)` for every function of the program, where `name` is the fully
+    /// qualified name accepted by `call` and `n_params` excludes implicit builtin parameters.
+    fn functions(&self) -> Vec<(String, usize)> {
+        self.program
+            .functions()
+            .into_iter()
+            .map(|(name, n_params)| (name.to_string(), n_params))
+            .collect()
+    }
+
+    /// Runs `function` with the given arguments, reusing the compiled program.
+    ///
+    /// Raises `CairoPanic` if the function panics, unless `raise_on_panic` is false, in which case
+    /// the returned result is marked as `panicked`. With `context`, the function runs in this
+    /// `ExecutionContext` instead of the one of the program.
+    #[pyo3(signature = (function, arguments=None, *, raise_on_panic=true, context=None))]
+    fn call(
+        &self,
+        function: &str,
+        arguments: Option<PyObject>,
+        raise_on_panic: bool,
+        context: Option<PyExecutionContext>,
+        py: Python<'_>,
+    ) -> PyResult<PyRunResult> {
+        let arguments = match arguments {
+            Some(arguments) => python_to_json(arguments, py)?,
+            None => "[]".to_string(),
+        };
+
+        let program = &self.program;
+        let (function, run) = py
+            .allow_threads(|| {
+                let function = program.function(Some(function))?;
+                let program_args = program.encode_args(function, &arguments)?;
+                let context = match &context {
+                    Some(context) => &context.context,
+                    None => program.context(),
+                };
+                let run = program.run_in_context(function, &program_args, context)?;
+                self.record_gas(function, &program_args, &run.result);
+                Ok::<_, anyhow::Error>((function, run))
+            })
+            .map_err(|e| to_py_err(e, py))?;
+
+        PyRunResult::new(program, function, run, raise_on_panic, py)
+    }
+
+    /// Runs `function` and decodes its return value according to its Cairo return type.
+    ///
+    /// Integers and felts are returned as `int`, `ByteArray` as `str`, arrays as `list`, tuples as
+    /// `tuple`, structs as a `tuple` of their fields, as Sierra programs don't record member names,
+    /// and enums as a `{"variant": index, "value": value}` dict.
+    #[pyo3(signature = (function, arguments=None))]
+    fn call_value(
+        &self,
+        function: &str,
+        arguments: Option<PyObject>,
+        py: Python<'_>,
+    ) -> PyResult<PyObject> {
+        let arguments = match arguments {
+            Some(arguments) => python_to_json(arguments, py)?,
+            None => "[]".to_string(),
+        };
+
+        let program = &self.program;
+        let value = py
+            .allow_threads(|| program.call_value(function, &arguments))
+            .map_err(|e| to_py_err(e, py))?;
+
+        cairo_value_to_py(value, py)
+    }
+
+    /// Checks that `function` returns the same values as the Python function `reference`, on
+    /// `cases` inputs generated from its parameter types.
+    ///
+    /// `reference` is called with the generated arguments decoded as by `call_value`, and its
+    /// result compared with the decoded return value of `function`. Raises `AssertionError` with
+    /// both outputs side by side for the smallest input found on which they differ.
+    ///
+    /// With `corpus`, the inputs recorded for `function` in this failure corpus file are checked
+    /// first, and the failing input is recorded in it.
+    #[pyo3(signature = (function, reference, *, cases=256, max_array_len=10, max_string_len=64, corpus=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn differential_test(
+        &self,
+        function: &str,
+        reference: PyObject,
+        cases: u32,
+        max_array_len: usize,
+        max_string_len: usize,
+        corpus: Option<PathBuf>,
+        py: Python<'_>,
+    ) -> PyResult<()> {
+        let config = DifferentialConfig {
+            cases,
+            strategy: StrategyConfig {
+                max_array_len,
+                max_string_len,
+            },
+            corpus,
+        };
+
+        let program = &self.program;
+        py.allow_threads(|| {
+            program.differential_test_with(function, &config, |args, output| {
+                Python::with_gil(|py| {
+                    let args = args
+                        .iter()
+                        .map(|arg| cairo_value_to_py(arg.clone(), py))
+                        .collect::<PyResult<Vec<_>>>()?;
+                    let expected = reference.call1(py, PyTuple::new_bound(py, args))?;
+                    let cairo = match output {
+                        Ok(value) => {
+                            let value = cairo_value_to_py(value.clone(), py)?;
+                            if value.bind(py).eq(&expected)? {
+                                return Ok(None);
+                            }
+                            value.bind(py).repr()?.to_string()
+                        }
+                        Err(_) => render_output(output),
+                    };
+                    Ok(Some((cairo, expected.bind(py).repr()?.to_string())))
+                })
+            })
+        })
+        .map_err(|e| to_py_err(e, py))
+    }
+
+    /// Calls the functions of the failing cases stored in the failure corpus file at `path` with
+    /// their recorded arguments.
+    ///
+    /// Returns `(function, arguments, recorded, current)` for every case, where `arguments` is a
+    /// JSON array and `recorded` and `current` are the outputs when the failure was found and
+    /// now.
+    fn replay_corpus(
+        &self,
+        path: PathBuf,
+        py: Python<'_>,
+    ) -> PyResult<Vec<(String, String, String, String)>> {
+        let corpus = FailureCorpus::load(&path).map_err(|e| to_py_err(e, py))?;
+        let program = &self.program;
+        let report = py.allow_threads(|| program.replay_corpus(&corpus));
+        Ok(report
+            .0
+            .into_iter()
+            .map(|replayed| {
+                (
+                    replayed.case.function,
+                    replayed.case.args.to_string(),
+                    replayed.case.result,
+                    replayed.result,
+                )
+            })
+            .collect())
+    }
+
+    /// Runs `function` and compares its return value, rendered as text, with the snapshot file
+    /// at `path`.
+    ///
+    /// Raises `AssertionError` with a diff if the file is missing or different. With `update`
+    /// `"new"`, the default, the new snapshot is written next to it as `<path>.new` for review;
+    /// `"always"` overwrites the file instead of failing and `"no"` writes nothing. The default
+    /// can be changed with the `CAIRO_SNAPSHOT_UPDATE` environment variable.
+    #[pyo3(signature = (path, function, arguments=None, *, update=None))]
+    fn assert_snapshot(
+        &self,
+        path: PathBuf,
+        function: &str,
+        arguments: Option<PyObject>,
+        update: Option<&str>,
+        py: Python<'_>,
+    ) -> PyResult<()> {
+        let arguments = match arguments {
+            Some(arguments) => python_to_json(arguments, py)?,
+            None => "[]".to_string(),
+        };
+
+        let program = &self.program;
+        py.allow_threads(|| {
+            let update = match update {
+                Some(update) => update.parse()?,
+                None => SnapshotUpdate::from_env()?,
+            };
+            let snapshot = program.snapshot(function, &arguments)?;
+            snapshot::assert_snapshot(&path, &snapshot, update)
+        })
+        .map_err(|e| to_py_err(e, py))
+    }
+
+    /// Decodes `event` as a value of the struct or enum `type_name`, e.g. `"Transfer"` or
+    /// `"my_contract::Transfer"`, as returned by `call_value`.
+    ///
+    /// The event must have been emitted through the variant of a contract's `Event` enum named
+    /// after the type, with the type's `#[key]` members first.
+    fn decode_event(
+        &self,
+        event: &Bound<'_, PyEvent>,
+        type_name: &str,
+        py: Python<'_>,
+    ) -> PyResult<PyObject> {
+        let value = self
+            .program
+            .decode_event(&event.get().event, type_name)
+            .map_err(|e| to_py_err(e, py))?;
+        cairo_value_to_py(value, py)
+    }
+
+    /// Checks that `result` emitted an event of type `type_name` with the given `fields`, e.g.
+    /// `[sender, recipient, amount]` for a struct, in the format of `call` arguments.
+    ///
+    /// Raises `AssertionError` listing the events of that type emitted otherwise.
+    fn assert_emitted(
+        &self,
+        result: &Bound<'_, PyRunResult>,
+        type_name: &str,
+        fields: PyObject,
+        py: Python<'_>,
+    ) -> PyResult<()> {
+        let fields = python_to_json(fields, py)?;
+        let events = result
+            .get()
+            .events
+            .iter()
+            .map(|event| event.get().event.clone())
+            .collect::<Vec<_>>();
+
+        let program = &self.program;
+        py.allow_threads(|| program.assert_emitted(&events, type_name, &fields))
+            .map_err(|e| to_py_err(e, py))
+    }
+
+    /// Runs `function` once per argument list of `arguments_list`, in parallel.
+    ///
+    /// Returns the results in input order. A failing case doesn't stop the batch: its entry is the
+    /// exception instance instead of a result. Panics are failures unless `raise_on_panic` is
+    /// false.
+    #[pyo3(signature = (function, arguments_list, *, raise_on_panic=true))]
+    fn call_batch(
+        &self,
+        function: &str,
+        arguments_list: Vec<PyObject>,
+        raise_on_panic: bool,
+        py: Python<'_>,
+    ) -> PyResult<Vec<PyObject>> {
+        let arguments_list = arguments_list
+            .into_iter()
+            .map(|arguments| python_to_json(arguments, py))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let program = &self.program;
+        let (function, mut cases, results) = py
+            .allow_threads(|| {
+                let function = program.function(Some(function))?;
+                // Arguments that don't match the parameter types fail their own case only, like
+                // failing runs.
+                let mut cases = arguments_list
+                    .iter()
+                    .map(|arguments| program.encode_args(function, arguments))
+                    .collect::<Vec<_>>();
+                let program_args = cases
+                    .iter_mut()
+                    .filter_map(|case| case.as_mut().ok().map(std::mem::take))
+                    .collect::<Vec<_>>();
+                let results = program.run_batch(function, &program_args);
+                for (args, run) in program_args.iter().zip(&results) {
+                    if let Ok(run) = run {
+                        self.record_gas(function, args, &run.result);
+                    }
+                }
+                Ok::<_, anyhow::Error>((function, cases, results))
+            })
+            .map_err(|e| to_py_err(e, py))?;
+
+        let mut results = results.into_iter();
+        Ok(cases
+            .drain(..)
+            .map(|case| {
+                let result = case
+                    .and_then(|_| results.next().expect("one result per valid case"))
+                    .map_err(|e| to_py_err(e, py))
+                    .and_then(|run| PyRunResult::new(program, function, run, raise_on_panic, py));
+                match result {
+                    Ok(result) => result.into_py(py),
+                    Err(e) => e.into_value(py).into_py(py),
+                }
+            })
+            .collect())
+    }
+}
+
+/// An in-memory Starknet state to test contracts without a devnet: the contract classes that Scarb
+/// compiles for `starknet-contract` targets (`*.contract_class.json`) are declared and deployed in
+/// it, then called by entry point name, each call starting from the state the previous ones left.
+#[pyclass(name = "ContractEnv", module = "scarb_pytest")]
+struct PyContractEnv {
+    env: ContractEnv,
+}
+
+#[pymethods]
+impl PyContractEnv {
+    /// Contracts are called from the caller address of `context`, and start from its storage.
+    /// Every call has `available_gas`, unlimited by default.
+    #[new]
+    #[pyo3(signature = (context=None, available_gas=None))]
+    fn new(
+        context: Option<PyExecutionContext>,
+        available_gas: Option<usize>,
+        py: Python<'_>,
+    ) -> PyResult<Self> {
+        let context = context.map(|context| context.context).unwrap_or_default();
+        let mut env = ContractEnv::new(context).map_err(|e| to_py_err(e, py))?;
+        if let Some(available_gas) = available_gas {
+            env = env.with_available_gas(available_gas);
+        }
+        Ok(Self { env })
+    }
+
+    /// Declares the contract class in the JSON file at `path`, and returns its class hash.
+    fn declare(&mut self, path: PathBuf, py: Python<'_>) -> PyResult<BigUint> {
+        let class = py
+            .allow_threads(|| ContractClass::load(&path))
+            .map_err(|e| to_py_err(e, py))?;
+        Ok(self.env.declare(class).to_biguint())
+    }
+
+    /// Deploys a contract of the declared class `class_hash`, calling its constructor with
+    /// `arguments`, in the format of `Program.call` arguments. Returns the contract address.
+    /// Raises `CairoPanic` if the constructor panics.
+    #[pyo3(signature = (class_hash, arguments=None))]
+    fn deploy(
+        &mut self,
+        class_hash: BigUint,
+        arguments: Option<PyObject>,
+        py: Python<'_>,
+    ) -> PyResult<BigUint> {
+        let arguments = match arguments {
+            Some(arguments) => python_to_json(arguments, py)?,
+            None => "[]".to_string(),
+        };
+
+        let env = &mut self.env;
+        let address = py
+            .allow_threads(|| env.deploy(class_hash.into(), &arguments))
+            .map_err(|e| to_py_err(e, py))?;
+        Ok(address.to_biguint())
+    }
+
+    /// Calls the entry point `entry_point` of the contract at `address`, like `Program.call`.
+    /// `values` are the felts returned by the entry point, and `value` their decoding according to
+    /// its return types in the ABI, structs being a `dict` keyed by the member names of the ABI.
+    /// The changes made by the call are kept unless it panics.
+    #[pyo3(signature = (address, entry_point, arguments=None, *, raise_on_panic=true))]
+    fn call(
+        &mut self,
+        address: BigUint,
+        entry_point: &str,
+        arguments: Option<PyObject>,
+        raise_on_panic: bool,
+        py: Python<'_>,
+    ) -> PyResult<PyRunResult> {
+        let arguments = match arguments {
+            Some(arguments) => python_to_json(arguments, py)?,
+            None => "[]".to_string(),
+        };
+
+        let address = Felt252::from(address);
+        let env = &mut self.env;
+        let call = py
+            .allow_threads(|| env.invoke(address, entry_point, &arguments))
+            .map_err(|e| to_py_err(e, py))?;
+        let gas_consumed = self.env.gas_consumed(&call.run.result);
+        let class = self.env.class_at(address).map_err(|e| to_py_err(e, py))?;
+        let retdata = call.retdata;
+        PyRunResult::from_run(call.run, gas_consumed, raise_on_panic, py, |_, _| {
+            let value = class.decode_retdata(entry_point, &retdata).ok();
+            (retdata, value)
+        })
+    }
+
+    /// Returns the value at storage address `key` of the contract at `address`.
+    fn read_storage(&self, address: BigUint, key: BigUint, py: Python<'_>) -> PyResult<BigUint> {
+        let value = self
+            .env
+            .storage(address.into(), key.into())
+            .map_err(|e| to_py_err(e, py))?;
+        Ok(value.to_biguint())
+    }
+
+    /// Same as `Program.assert_emitted`, for the events of `result` emitted by the contract at
+    /// `address`, decoded with its class.
+    fn assert_emitted(
+        &self,
+        result: &Bound<'_, PyRunResult>,
+        address: BigUint,
+        type_name: &str,
+        fields: PyObject,
+        py: Python<'_>,
+    ) -> PyResult<()> {
+        let fields = python_to_json(fields, py)?;
+        let events = result
+            .get()
+            .events
+            .iter()
+            .map(|event| event.get().event.clone())
+            .collect::<Vec<_>>();
+
+        let env = &self.env;
+        py.allow_threads(|| env.assert_emitted(address.into(), &events, type_name, &fields))
+            .map_err(|e| to_py_err(e, py))
+    }
+}
+
+/// The Starknet context a call runs in: the execution info read by functions with syscalls such
+/// as `get_block_number` or `get_caller_address`, and the initial contract storage. Everything is
+/// zero by default.
+#[pyclass(name = "ExecutionContext", module = "scarb_pytest", frozen)]
+#[derive(Clone)]
+struct PyExecutionContext {
+    context: ExecutionContext,
+}
+
+#[pymethods]
+impl PyExecutionContext {
+    /// Addresses, hashes and the chain ID are ints, e.g. `int.from_bytes(b"SN_SEPOLIA")` for a
+    /// chain ID. `storage` maps `(contract_address, key)` pairs to their initial value, and
+    /// `mocked_calls` maps `(contract_address, selector)` pairs to the list of felts returned by
+    /// calls to this entry point instead of running it.
+    #[new]
+    #[pyo3(signature = (*, block_number=0, block_timestamp=0, sequencer_address=None, caller_address=None, contract_address=None, transaction_hash=None, chain_id=None, storage=None, mocked_calls=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        block_number: u64,
+        block_timestamp: u64,
+        sequencer_address: Option<BigUint>,
+        caller_address: Option<BigUint>,
+        contract_address: Option<BigUint>,
+        transaction_hash: Option<BigUint>,
+        chain_id: Option<BigUint>,
+        storage: Option<BTreeMap<(BigUint, BigUint), BigUint>>,
+        mocked_calls: Option<BTreeMap<(BigUint, BigUint), Vec<BigUint>>>,
+    ) -> Self {
+        let felt = |value: Option<BigUint>| value.map(Felt252::from).unwrap_or_default();
+        let mut context = ExecutionContext {
+            block_number,
+            block_timestamp,
+            sequencer_address: felt(sequencer_address),
+            caller_address: felt(caller_address),
+            contract_address: felt(contract_address),
+            transaction_hash: felt(transaction_hash),
+            chain_id: felt(chain_id),
+            ..Default::default()
+        };
+        for ((contract_address, key), value) in storage.unwrap_or_default() {
+            context = context.with_storage(contract_address.into(), key.into(), value.into());
+        }
+        for ((contract_address, selector), ret_data) in mocked_calls.unwrap_or_default() {
+            let ret_data = ret_data.into_iter().map(Felt252::from).collect();
+            context = context.with_mocked_call(contract_address.into(), selector.into(), ret_data);
+        }
+        Self { context }
+    }
+
+    #[getter]
+    fn block_number(&self) -> u64 {
+        self.context.block_number
+    }
+
+    #[getter]
+    fn block_timestamp(&self) -> u64 {
+        self.context.block_timestamp
+    }
+
+    #[getter]
+    fn sequencer_address(&self) -> BigUint {
+        self.context.sequencer_address.to_biguint()
+    }
+
+    #[getter]
+    fn caller_address(&self) -> BigUint {
+        self.context.caller_address.to_biguint()
+    }
+
+    #[getter]
+    fn contract_address(&self) -> BigUint {
+        self.context.contract_address.to_biguint()
+    }
+
+    #[getter]
+    fn transaction_hash(&self) -> BigUint {
+        self.context.transaction_hash.to_biguint()
+    }
+
+    #[getter]
+    fn chain_id(&self) -> BigUint {
+        self.context.chain_id.to_biguint()
+    }
+
+    #[getter]
+    fn storage(&self) -> BTreeMap<(BigUint, BigUint), BigUint> {
+        self.context
+            .storage
+            .iter()
+            .map(|((contract_address, key), value)| {
+                (
+                    (contract_address.to_biguint(), key.to_biguint()),
+                    value.to_biguint(),
+                )
+            })
+            .collect()
+    }
+
+    #[getter]
+    fn mocked_calls(&self) -> BTreeMap<(BigUint, BigUint), Vec<BigUint>> {
+        self.context
+            .mocked_calls
+            .iter()
+            .map(|((contract_address, selector), ret_data)| {
+                (
+                    (contract_address.to_biguint(), selector.to_biguint()),
+                    ret_data.iter().map(|felt| felt.to_biguint()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        let context = &self.context;
+        format!(
+            "ExecutionContext(block_number={}, block_timestamp={}, caller_address={:#x}, \
+             contract_address={:#x}, storage={})",
+            context.block_number,
+            context.block_timestamp,
+            context.caller_address,
+            context.contract_address,
+            context.storage.len()
+        )
+    }
+}
+
+/// The VM resources used by a run, which determine its proving cost.
+#[pyclass(name = "ExecutionResources", module = "scarb_pytest", frozen, get_all)]
+struct PyExecutionResources {
+    steps: usize,
+    memory_holes: usize,
+    /// The number of instances of each builtin used, by name, e.g. `range_check`.
+    builtins: BTreeMap<String, usize>,
+    /// The number of calls to each syscall, by name.
+    syscalls: BTreeMap<String, usize>,
+}
+
+#[pymethods]
+impl PyExecutionResources {
+    fn __repr__(&self) -> String {
+        let builtins = self
+            .builtins
+            .iter()
+            .map(|(name, count)| format!("{name}={count}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "ExecutionResources(steps={}, memory_holes={}, {builtins})",
+            self.steps, self.memory_holes
+        )
+    }
+}
+
+impl From<ExecutionResources> for PyExecutionResources {
+    fn from(resources: ExecutionResources) -> Self {
+        Self {
+            steps: resources.steps,
+            memory_holes: resources.memory_holes,
+            builtins: resources.builtins,
+            syscalls: resources.syscalls,
+        }
+    }
+}
+
+/// The Cairo source lines executed by the calls of a `Program`. `str()` formats the line
+/// coverage of each file.
+#[pyclass(name = "CoverageReport", module = "scarb_pytest")]
+struct PyCoverageReport {
+    report: CoverageReport,
+}
+
+#[pymethods]
+impl PyCoverageReport {
+    /// Adds the lines of `other`, e.g. from another program of the same package.
+    fn merge(&mut self, other: &PyCoverageReport) {
+        self.report.merge(other.report.clone());
+    }
+
+    /// Returns the number of VM steps executed per line of `file`, for lines generating code.
+    fn lines(&self, file: &str) -> Option<BTreeMap<usize, usize>> {
+        self.report.file(file).cloned()
+    }
+
+    fn write_lcov(&self, path: PathBuf, py: Python<'_>) -> PyResult<()> {
+        self.report.write_lcov(&path).map_err(|e| to_py_err(e, py))
+    }
+
+    fn to_lcov(&self) -> String {
+        self.report.to_lcov()
+    }
+
+    fn __str__(&self) -> String {
+        self.report.to_string()
+    }
+}
+
+/// The VM steps executed per call stack by the calls of a `Program`. `str()` formats the ten
+/// most expensive functions.
+#[pyclass(name = "ProfileReport", module = "scarb_pytest")]
+struct PyProfileReport {
+    report: ProfileReport,
+}
+
+#[pymethods]
+impl PyProfileReport {
+    /// Adds the call stacks of `other`, e.g. from another program of the same package.
+    fn merge(&mut self, other: &PyProfileReport) {
+        self.report.merge(other.report.clone());
+    }
+
+    /// Returns `(name, total_steps, self_steps)` for the `n` most expensive functions, where
+    /// `self_steps` excludes the steps of the functions called.
+    #[pyo3(signature = (n=10))]
+    fn top(&self, n: usize) -> Vec<(String, usize, usize)> {
+        self.report
+            .top(n)
+            .0
+            .into_iter()
+            .map(|function| (function.name, function.total_steps, function.self_steps))
+            .collect()
+    }
+
+    /// Writes the report in the folded stacks format read by flamegraph tools.
+    fn write_folded(&self, path: PathBuf, py: Python<'_>) -> PyResult<()> {
+        self.report.write_folded(&path).map_err(|e| to_py_err(e, py))
+    }
+
+    fn to_folded(&self) -> String {
+        self.report.to_folded()
+    }
+
+    fn __str__(&self) -> String {
+        self.report.top(10).to_string()
+    }
+}
+
+/// The gas consumed by functions per argument set, stored in a snapshot file checked into the
+/// repository to catch gas regressions.
+#[pyclass(name = "GasSnapshot", module = "scarb_pytest")]
+#[derive(Default)]
+struct PyGasSnapshot {
+    snapshot: GasSnapshot,
+}
+
+#[pymethods]
+impl PyGasSnapshot {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the snapshot file at `path`, or returns an empty snapshot if it doesn't exist yet.
+    #[staticmethod]
+    fn load(path: PathBuf, py: Python<'_>) -> PyResult<Self> {
+        let snapshot = GasSnapshot::load(&path).map_err(|e| to_py_err(e, py))?;
+        Ok(Self { snapshot })
+    }
+
+    fn save(&self, path: PathBuf, py: Python<'_>) -> PyResult<()> {
+        self.snapshot.save(&path).map_err(|e| to_py_err(e, py))
+    }
+
+    /// Adds the entries of `other`, replacing the values recorded for the same calls.
+    fn merge(&mut self, other: &PyGasSnapshot) {
+        self.snapshot.merge(other.snapshot.clone());
+    }
+
+    /// Compares this snapshot with `baseline`, calls whose gas increased by more than
+    /// `threshold` percent being regressions.
+    #[pyo3(signature = (baseline, threshold=0.0))]
+    fn compare(&self, baseline: &PyGasSnapshot, threshold: f64) -> PyGasReport {
+        PyGasReport {
+            report: self.snapshot.compare(&baseline.snapshot, threshold),
+        }
+    }
+}
+
+/// The differences between a `GasSnapshot` and its baseline. `str()` formats them as a table.
+#[pyclass(name = "GasReport", module = "scarb_pytest", frozen)]
+struct PyGasReport {
+    report: GasReport,
 }
 
-fn python_to_json(obj: Py<PyAny>, py: Python<'_>) -> PyResult<String> {
-    let json = py.import_bound("json")?;
-    let dumps = json.getattr("dumps")?;
-    println!("Debug: Dumping Python object to JSON");
-    let json_str = dumps.call1((obj,))?.extract::<String>()?;
-    println!("Debug: JSON string: {:?}", json_str);
+#[pymethods]
+impl PyGasReport {
+    #[getter]
+    fn has_regressions(&self) -> bool {
+        self.report.has_regressions()
+    }
+
+    /// The `(function, arguments, before, after)` calls whose gas increased beyond the threshold.
+    #[getter]
+    fn regressions(&self) -> Vec<(String, String, Option<u64>, u64)> {
+        self.report
+            .regressions()
+            .map(|diff| (diff.function.clone(), diff.args.clone(), diff.before, diff.after))
+            .collect()
+    }
+
+    fn __str__(&self) -> String {
+        self.report.to_string()
+    }
+}
 
-    Ok(json_str)
+fn cairo_value_to_py(value: CairoValue, py: Python<'_>) -> PyResult<PyObject> {
+    Ok(match value {
+        CairoValue::Felt(value) => value.to_biguint().into_py(py),
+        CairoValue::Uint(value) => value.into_py(py),
+        CairoValue::Int(value) => value.into_py(py),
+        CairoValue::Bool(value) => value.into_py(py),
+        CairoValue::String(value) => value.into_py(py),
+        CairoValue::Array(values) => values
+            .into_iter()
+            .map(|value| cairo_value_to_py(value, py))
+            .collect::<PyResult<Vec<_>>>()?
+            .into_py(py),
+        CairoValue::Tuple(values) => PyTuple::new_bound(
+            py,
+            values
+                .into_iter()
+                .map(|value| cairo_value_to_py(value, py))
+                .collect::<PyResult<Vec<_>>>()?,
+        )
+        .into_py(py),
+        CairoValue::Struct {
+            members: Some(members),
+            fields,
+            ..
+        } => {
+            let dict = PyDict::new_bound(py);
+            for (member, field) in members.into_iter().zip(fields) {
+                dict.set_item(member, cairo_value_to_py(field, py)?)?;
+            }
+            dict.into_py(py)
+        }
+        CairoValue::Struct { fields, .. } => cairo_value_to_py(CairoValue::Tuple(fields), py)?,
+        CairoValue::Enum { variant, value, .. } => {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("variant", variant)?;
+            dict.set_item("value", cairo_value_to_py(*value, py)?)?;
+            dict.into_py(py)
+        }
+        CairoValue::Raw { felts, .. } => felts
+            .into_iter()
+            .map(|felt| felt.to_biguint())
+            .collect::<Vec<_>>()
+            .into_py(py),
+    })
 }
 
-fn json_to_cairo_args(json_str: String) -> PyResult<deserialization::Args> {
-    serde_json::from_str(&json_str)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("JSON parsing error: {}", e)))
+/// Converts a Python object to the JSON arguments expected by `CairoProgram`.
+///
+/// Integers that don't fit in 64 bits are converted to decimal strings, which JSON numbers can't
+/// represent exactly.
+fn python_to_json(obj: PyObject, py: Python<'_>) -> PyResult<String> {
+    let json = python_to_json_value(obj.bind(py))?.to_string();
+    debug!("JSON string: {:?}", json);
+
+    Ok(json)
+}
+
+fn python_to_json_value(obj: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    use serde_json::Value;
+
+    Ok(if obj.is_none() {
+        Value::Null
+    } else if let Ok(value) = obj.downcast::<PyBool>() {
+        Value::Bool(value.is_true())
+    } else if let Ok(value) = obj.downcast::<PyInt>() {
+        match value.extract::<i64>() {
+            Ok(value) => Value::from(value),
+            Err(_) => Value::String(value.str()?.to_string()),
+        }
+    } else if let Ok(value) = obj.downcast::<PyString>() {
+        Value::String(value.to_str()?.to_string())
+    } else if let Ok(dict) = obj.downcast::<PyDict>() {
+        dict.iter()
+            .map(|(key, value)| Ok((key.str()?.to_string(), python_to_json_value(&value)?)))
+            .collect::<PyResult<serde_json::Map<_, _>>>()?
+            .into()
+    } else if let Ok(values) = obj.downcast::<PyList>() {
+        values
+            .iter()
+            .map(|value| python_to_json_value(&value))
+            .collect::<PyResult<Vec<_>>>()?
+            .into()
+    } else if let Ok(values) = obj.downcast::<PyTuple>() {
+        values
+            .iter()
+            .map(|value| python_to_json_value(&value))
+            .collect::<PyResult<Vec<_>>>()?
+            .into()
+    } else {
+        return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "Unsupported argument type: {}",
+            obj.get_type().name()?
+        )));
+    })
 }
 
 #[pymodule]
@@ -168,39 +1430,253 @@ fn scarb_pytest(m: &Bound<'_, PyModule>) -> PyResult<()> {
     std::env::set_var("SCARB_TARGET_DIR", "target");
     std::env::set_var("SCARB_PROFILE", "dev");
     m.add_function(wrap_pyfunction!(run_scarb_pytest_py, m)?)?;
+    m.add_function(wrap_pyfunction!(pending_snapshots, m)?)?;
+    m.add_function(wrap_pyfunction!(accept_snapshots, m)?)?;
+    m.add_function(wrap_pyfunction!(reject_snapshots, m)?)?;
+    m.add_class::<PyProgram>()?;
+    m.add_class::<PyCairoPanic>()?;
+    m.add_class::<PyRunResult>()?;
+    m.add_class::<PyEvent>()?;
+    m.add_class::<PyL2ToL1Message>()?;
+    m.add_class::<PyContractEnv>()?;
+    m.add_class::<PyExecutionContext>()?;
+    m.add_class::<PyExecutionResources>()?;
+    m.add_class::<PyCoverageReport>()?;
+    m.add_class::<PyProfileReport>()?;
+    m.add_class::<PyGasSnapshot>()?;
+    m.add_class::<PyGasReport>()?;
     Ok(())
 }
 
+impl Args {
+    /// Returns the output format requested on the command line.
+    pub fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            OutputFormat::Text
+        }
+    }
+
+    /// Returns the Starknet context requested on the command line.
+    pub fn execution_context(&self) -> ExecutionContext {
+        let mut context = ExecutionContext {
+            block_number: self.block_number.unwrap_or_default(),
+            block_timestamp: self.block_timestamp.unwrap_or_default(),
+            sequencer_address: self.sequencer_address.unwrap_or_default(),
+            caller_address: self.caller_address.unwrap_or_default(),
+            contract_address: self.contract_address.unwrap_or_default(),
+            transaction_hash: self.transaction_hash.unwrap_or_default(),
+            chain_id: self.chain_id.unwrap_or_default(),
+            ..Default::default()
+        };
+        for (contract_address, key, value) in &self.storage {
+            context = context.with_storage(*contract_address, *key, *value);
+        }
+        context
+    }
+}
+
+/// Parses a `--storage` value: `CONTRACT:KEY=VALUE`.
+fn parse_storage_value(value: &str) -> Result<(Felt252, Felt252, Felt252)> {
+    let (location, value) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected `CONTRACT:KEY=VALUE`"))?;
+    let (contract_address, key) = location
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected `CONTRACT:KEY=VALUE`"))?;
+    let felt = |felt: &str| {
+        felt.trim()
+            .parse::<Felt252>()
+            .map_err(|_| anyhow!("invalid felt `{felt}`"))
+    };
+    Ok((felt(contract_address)?, felt(key)?, felt(value)?))
+}
+
 pub fn main_inner(ui: &Ui, args: Args) -> Result<()> {
-    let result = inner_runner(ui, args.clone())?;
+    let program = load_program(ui, &args)?;
+    if let Some(path) = &args.replay_corpus {
+        let corpus = FailureCorpus::load(path.as_std_path())?;
+        let report = program.replay_corpus(&corpus);
+        if !args.json {
+            println!("{report}");
+        }
+        let reproduced = report.0.iter().filter(|replayed| replayed.reproduces()).count();
+        ensure!(reproduced == 0, "{reproduced} failure cases of corpus {path} still reproduce");
+        return Ok(());
+    }
+
+    let CairoRun {
+        result,
+        output,
+        events,
+        l2_to_l1_messages,
+    } = run_program(&program, &args)?;
+    let gas_snapshot = match &args.gas_snapshot {
+        Some(_) => Some(record_gas(&program, &args, &result)?),
+        None => None,
+    };
 
     let (print_full_memory, gas_defined) = (args.clone().print_full_memory, args.clone().available_gas.is_some());
 
     ui.print(Summary {
         result,
+        // Already printed as the function ran.
+        output: if args.stream_output { String::new() } else { output },
+        events,
+        l2_to_l1_messages,
         print_full_memory,
+        print_resources: args.print_resources,
         gas_defined,
     });
 
+    if let (Some(path), Some(gas_snapshot)) = (&args.gas_snapshot, gas_snapshot) {
+        check_gas_snapshot(ui, &args, path, gas_snapshot)?;
+    }
+
+    if let Some(path) = &args.coverage {
+        let mut report = program.coverage_report()?;
+        report.retain_under(&source_root()?);
+        report.write_lcov(path.as_std_path())?;
+        if !args.json {
+            println!("{report}");
+        }
+    }
+
+    if let Some(path) = &args.profile {
+        let report = program.profile_report()?;
+        report.write_folded(path.as_std_path())?;
+        if !args.json {
+            println!("{}", report.top(args.profile_top));
+        }
+    }
+
     Ok(())
 }
 
-pub fn inner_runner(ui: &Ui, args: Args) -> Result<RunResultStarknet> {
+/// Returns the root of the sources to report coverage for: the directory of the Scarb manifest
+/// when run by Scarb, the current directory otherwise.
+fn source_root() -> Result<PathBuf> {
+    match env::var("SCARB_MANIFEST_PATH") {
+        Ok(manifest_path) => Ok(PathBuf::from(manifest_path)
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_default()),
+        Err(_) => Ok(env::current_dir()?),
+    }
+}
+
+pub fn inner_runner(ui: &Ui, args: Args) -> Result<CairoRun> {
+    let program = load_program(ui, &args)?;
+    run_program(&program, &args)
+}
+
+/// Runs the function selected by `args`. Fails with a [`CairoPanic`] if it panicked.
+pub fn run_scarb_pytest(args: Args) -> Result<String> {
+    let ui = Ui::new(args.verbose.clone().into(), OutputFormat::Text);
+    let run = inner_runner(&ui, args)?;
+    match run.result.value {
+        RunResultValue::Success(values) => {
+            let values = values.iter().map(ToString::to_string).collect::<Vec<_>>();
+            Ok(format!("Run completed successfully, returning [{}]", values.join(", ")))
+        }
+        RunResultValue::Panic(values) => {
+            Err(CairoPanic::new(values).with_output(run.output).into())
+        }
+    }
+}
+
+/// Runs the function selected by `args` with the program arguments of `args`.
+pub fn run_program(program: &CairoProgram, args: &Args) -> Result<CairoRun> {
+    let function = program.function(args.function.as_deref())?;
+    let program_args = program_args(program, function, args)?;
+
+    let start = Instant::now();
+    let res = program
+        .run(function, &program_args)
+        .with_context(|| "failed to run the function");
+    debug!("Time taken to run the function: {:?}", start.elapsed());
+    res
+}
+
+/// Returns the program arguments of `args`, read from `--arguments-file` if given, encoded
+/// according to the parameter types of `function`.
+fn program_args(program: &CairoProgram, function: &Function, args: &Args) -> Result<Vec<Arg>> {
+    let arguments = match &args.arguments_file {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("failed to read arguments from file: {path}"))?,
+        None => args.arguments.clone(),
+    };
+    program.encode_args(function, &arguments)
+}
+
+/// Returns a gas snapshot holding the gas consumed by the run of the function selected by
+/// `args`.
+fn record_gas(program: &CairoProgram, args: &Args, result: &RunResultStarknet) -> Result<GasSnapshot> {
+    let function = program.function(args.function.as_deref())?;
+    let gas = program
+        .gas_consumed(result)
+        .ok_or_else(|| anyhow!("gas snapshots require gas metering to be enabled"))?;
+    let mut gas_snapshot = GasSnapshot::default();
+    gas_snapshot.record(&function.id.to_string(), &program_args(program, function, args)?, gas);
+    Ok(gas_snapshot)
+}
+
+/// Records `gas_snapshot` in the snapshot file at `path` with `--update-gas-snapshot`, otherwise
+/// compares it with the file, failing on gas regressions unless `--gas-warn-only` is given.
+fn check_gas_snapshot(ui: &Ui, args: &Args, path: &Utf8PathBuf, gas_snapshot: GasSnapshot) -> Result<()> {
+    let mut recorded = GasSnapshot::load(path.as_std_path())?;
+    if args.update_gas_snapshot {
+        recorded.merge(gas_snapshot);
+        recorded.save(path.as_std_path())?;
+        ui.print(Status::new("Updated", path.as_str()));
+        return Ok(());
+    }
+
+    let report = gas_snapshot.compare(&recorded, args.gas_threshold);
+    if !args.json {
+        println!("{report}");
+    }
+    if report.has_regressions() {
+        let message = format!(
+            "gas increased by more than {}% compared to snapshot {path}",
+            args.gas_threshold
+        );
+        if !args.gas_warn_only {
+            bail!(message);
+        }
+        ui.warn(message);
+    }
+    Ok(())
+}
+
+/// Builds the selected package if needed, then loads and compiles its Sierra program.
+pub fn load_program(ui: &Ui, args: &Args) -> Result<CairoProgram> {
+    let (sierra_program, available_gas) = load_sierra_program(ui, args)?;
+
+    debug!("Creating SierraCasmRunner");
+    let start = Instant::now();
+    let instrumentation = Instrumentation {
+        coverage: args.coverage.is_some(),
+        profile: args.profile.is_some(),
+    };
+    let program =
+        CairoProgram::instrumented(sierra_program, available_gas.value(), instrumentation)?
+            .with_context(args.execution_context())
+            .with_streamed_output(args.stream_output);
+    debug!("Time taken to create SierraCasmRunner: {:?}", start.elapsed());
+    Ok(program)
+}
+
+/// Builds the selected package if needed, then loads its Sierra program.
+fn load_sierra_program(ui: &Ui, args: &Args) -> Result<(ProgramArtifact, GasLimit)> {
     let start = Instant::now();
     let metadata = MetadataCommand::new().inherit_stderr().exec()?;
     let package = args.packages_filter.match_one(&metadata)?;
 
     let available_gas = GasLimit::parse(args.available_gas).with_metadata(&metadata, &package)?;
 
-    let program_args = match args.arguments_file {
-        Some(path) => serde_json::from_str::<deserialization::Args>(
-            &fs::read_to_string(path.clone())
-                .with_context(|| format!("failed to read arguments from file: {path}"))?,
-        )?,
-        None => args.arguments,
-    };
-
-    println!("Debug: Time taken to match package: {:?}", start.elapsed());
+    debug!("Time taken to match package: {:?}", start.elapsed());
     if !args.no_build {
         let filter = PackagesFilter::generate_for::<Metadata>(vec![package.clone()].iter());
         ScarbCommand::new()
@@ -236,107 +1712,18 @@ pub fn inner_runner(ui: &Ui, args: Args) -> Result<RunResultStarknet> {
     if available_gas.is_disabled() && sierra_program.program.requires_gas_counter() {
         bail!("program requires gas counter, please provide `--available-gas` argument");
     }
-    println!("Debug: Time taken to deserialize Sierra program: {:?}", start.elapsed());
-
-    println!("Debug: Creating SierraCasmRunner");
-    let start = Instant::now();
-    let runner = SierraCasmRunner::new(
-        sierra_program.program.clone(),
-        if available_gas.is_disabled() {
-            None
-        } else {
-            Some(Default::default())
-        },
-        Default::default(),
-        None,
-    )?;
-    println!("Debug: Time taken to create SierraCasmRunner: {:?}", start.elapsed());
-    let start = Instant::now();
-    let res = runner
-        .run_function_with_starknet_context(
-            main_function(&runner, &sierra_program, args.function.as_deref())?,
-            &program_args,
-            available_gas.value(),
-            StarknetState::default(),
-        )
-        .with_context(|| "failed to run the function");
-    println!("Debug: Time taken to run the function: {:?}", start.elapsed());
-    res
-}
-
-
-fn main_function<'a>(
-    runner: &'a SierraCasmRunner,
-    sierra_program: &'a ProgramArtifact,
-    name: Option<&str>,
-) -> Result<&'a Function> {
-    let executables = sierra_program
-        .debug_info
-        .as_ref()
-        .and_then(|di| di.executables.get(EXECUTABLE_NAME))
-        .cloned()
-        .unwrap_or_default();
-
-    // Prioritize `--function` args. First search among executables, then among all functions.
-    if let Some(name) = name {
-        let name = format!("::{name}");
-        return executables
-            .iter()
-            .find(|fid| {
-                fid.debug_name
-                    .as_deref()
-                    .map(|debug_name| debug_name.ends_with(&name))
-                    .unwrap_or_default()
-            })
-            .map(|fid| find_function(sierra_program, fid))
-            .unwrap_or_else(|| Ok(runner.find_function(&name)?));
-    }
-
-    // Then check if executables are unambiguous.
-    if executables.len() == 1 {
-        return find_function(
-            sierra_program,
-            executables.first().expect("executables can't be empty"),
-        );
-    }
-
-    // If executables are ambiguous, bail with error.
-    if executables.len() > 1 {
-        let names = executables
-            .iter()
-            .flat_map(|fid| fid.debug_name.clone())
-            .map(|name| name.to_string())
-            .collect::<Vec<_>>();
-        let msg = if names.is_empty() {
-            "please only mark a single function as executable or enable debug ids and choose function by name".to_string()
-        } else {
-            format!(
-                "please choose a function to run from the list:\n`{}`",
-                names.join("`, `")
-            )
-        };
-        bail!("multiple executable functions found\n{msg}");
-    }
-
-    // Finally check default function.
-    Ok(runner.find_function(DEFAULT_MAIN_FUNCTION)?)
-}
-
-fn find_function<'a>(
-    sierra_program: &'a ProgramArtifact,
-    fid: &FunctionId,
-) -> Result<&'a Function> {
-    sierra_program
-        .program
-        .funcs
-        .iter()
-        .find(|f| f.id == *fid)
-        .ok_or_else(|| anyhow!("function not found"))
+    debug!("Time taken to deserialize Sierra program: {:?}", start.elapsed());
+    Ok((sierra_program, available_gas))
 }
 
 struct Summary {
     result: RunResultStarknet,
+    /// What the function printed, if not printed as it ran.
+    output: String,
+    events: Vec<Event>,
+    l2_to_l1_messages: Vec<L2ToL1Message>,
     print_full_memory: bool,
+    print_resources: bool,
     gas_defined: bool,
 }
 
@@ -345,6 +1732,7 @@ impl Message for Summary {
     where
         Self: Sized,
     {
+        print!("{}", self.output);
         match self.result.value {
             RunResultValue::Success(values) => {
                 let values = values
@@ -366,12 +1754,23 @@ impl Message for Summary {
             }
         }
 
+        for event in &self.events {
+            println!("Emitted {event}");
+        }
+        for message in &self.l2_to_l1_messages {
+            println!("Sent {message}");
+        }
+
         if self.gas_defined {
             if let Some(gas) = self.result.gas_counter {
                 println!("Remaining gas: {gas}");
             }
         }
 
+        if self.print_resources {
+            println!("{}", ExecutionResources::from(&self.result.used_resources));
+        }
+
         if self.print_full_memory {
             print!("Full memory: [");
             for cell in &self.result.memory {
@@ -384,11 +1783,151 @@ impl Message for Summary {
         }
     }
 
-    fn structured<S: Serializer>(self, _ser: S) -> Result<S::Ok, S::Error>
+    fn structured<S: Serializer>(self, ser: S) -> Result<S::Ok, S::Error>
     where
         Self: Sized,
     {
-        todo!("JSON output is not implemented yet for this command")
+        let (status, values) = match &self.result.value {
+            RunResultValue::Success(values) => {
+                (RunStatus::Success, values.iter().map(StructuredFelt::from).collect())
+            }
+            RunResultValue::Panic(values) => {
+                (RunStatus::Panicked, values.iter().map(StructuredFelt::panic_data).collect())
+            }
+        };
+
+        StructuredSummary {
+            version: SUMMARY_SCHEMA_VERSION,
+            status,
+            output: self.output,
+            values,
+            events: self.events.iter().map(StructuredEvent::from).collect(),
+            l2_to_l1_messages: self
+                .l2_to_l1_messages
+                .iter()
+                .map(StructuredL2ToL1Message::from)
+                .collect(),
+            remaining_gas: self
+                .gas_defined
+                .then_some(self.result.gas_counter)
+                .flatten()
+                .map(|gas| gas.to_string()),
+            resources: self
+                .print_resources
+                .then(|| ExecutionResources::from(&self.result.used_resources)),
+            memory: self.print_full_memory.then(|| {
+                self.result
+                    .memory
+                    .iter()
+                    .map(|cell| cell.map(|value| value.to_string()))
+                    .collect()
+            }),
+        }
+        .serialize(ser)
+    }
+}
+
+/// Version of the JSON run summary schema, bumped on every breaking change to it.
+const SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+/// The JSON representation of a run summary.
+///
+/// Felts are serialized as strings, as they don't fit in JSON numbers.
+#[derive(Serialize)]
+struct StructuredSummary {
+    /// Version of this schema.
+    version: u32,
+    status: RunStatus,
+    /// What the function printed, if not printed as it ran.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    output: String,
+    /// The returned values if the run succeeded, the panic data otherwise.
+    values: Vec<StructuredFelt>,
+    /// The events emitted by the run, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    events: Vec<StructuredEvent>,
+    /// The messages to L1 sent by the run, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    l2_to_l1_messages: Vec<StructuredL2ToL1Message>,
+    /// The remaining gas, only present if a gas limit was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining_gas: Option<String>,
+    /// The VM resources used by the run. Only present with `--print-resources`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<ExecutionResources>,
+    /// The full memory, with `null` for unknown cells. Only present with `--print-full-memory`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory: Option<Vec<Option<String>>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum RunStatus {
+    Success,
+    Panicked,
+}
+
+#[derive(Serialize)]
+struct StructuredEvent {
+    from_address: StructuredFelt,
+    keys: Vec<StructuredFelt>,
+    data: Vec<StructuredFelt>,
+}
+
+impl From<&Event> for StructuredEvent {
+    fn from(event: &Event) -> Self {
+        Self {
+            from_address: StructuredFelt::from(&event.from_address),
+            keys: event.keys.iter().map(StructuredFelt::from).collect(),
+            data: event.data.iter().map(StructuredFelt::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StructuredL2ToL1Message {
+    from_address: StructuredFelt,
+    to_address: StructuredFelt,
+    payload: Vec<StructuredFelt>,
+}
+
+impl From<&L2ToL1Message> for StructuredL2ToL1Message {
+    fn from(message: &L2ToL1Message) -> Self {
+        Self {
+            from_address: StructuredFelt::from(&message.from_address),
+            to_address: StructuredFelt::from(&message.to_address),
+            payload: message.payload.iter().map(StructuredFelt::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StructuredFelt {
+    decimal: String,
+    hex: String,
+    /// The felt decoded as a non-empty Cairo short string. Only present for panic data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_string: Option<String>,
+}
+
+impl StructuredFelt {
+    /// Like [`StructuredFelt::from`], also decoding the felt as a short string, as panic data
+    /// usually holds short strings, unlike other felts.
+    fn panic_data(value: &Felt252) -> Self {
+        Self {
+            short_string: as_cairo_short_string(value).filter(|string| !string.is_empty()),
+            ..Self::from(value)
+        }
+    }
+}
+
+impl From<&Felt252> for StructuredFelt {
+    fn from(value: &Felt252) -> Self {
+        Self {
+            decimal: value.to_string(),
+            hex: value.to_hex_string(),
+            short_string: None,
+        }
     }
 }
 
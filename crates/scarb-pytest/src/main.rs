@@ -1,10 +1,12 @@
 use clap::Parser;
-use scarb_pytest::{Args, run_scarb_pytest};
+use scarb_pytest::{main_inner, Args};
+use scarb_ui::Ui;
 use anyhow::Result;
 
 fn main() -> Result<()> {
     let args: Args = Args::parse();
-    if let Err(err) = run_scarb_pytest(args) {
+    let ui = Ui::new(args.verbose.clone().into(), args.output_format());
+    if let Err(err) = main_inner(&ui, args) {
         eprintln!("Error: {}", err);
         std::process::exit(1);
     }
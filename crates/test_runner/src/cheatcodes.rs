@@ -0,0 +1,727 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::Result;
+use cairo_lang_casm::hints::{CoreHint, CoreHintBase, Hint, StarknetHint};
+use cairo_lang_casm::operand::ResOperand;
+use cairo_lang_runner::casm_run::{
+    cell_ref_to_relocatable, extract_relocatable, format_next_item, vm_get_range,
+};
+use cairo_lang_runner::{
+    build_hints_dict, Arg, CairoHintProcessor, RunResult, RunResultStarknet, RunResultValue,
+    SierraCasmRunner, StarknetState,
+};
+use cairo_lang_sierra::program::Function;
+use cairo_vm::hint_processor::hint_processor_definition::{HintProcessorLogic, HintReference};
+use cairo_vm::serde::deserialize_program::ApTracking;
+use cairo_vm::types::exec_scope::ExecutionScopes;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+use cairo_vm::vm::errors::hint_errors::HintError;
+use cairo_vm::vm::errors::vm_errors::VirtualMachineError;
+use cairo_vm::vm::runners::cairo_runner::{ResourceTracker, RunResources};
+use cairo_vm::vm::vm_core::VirtualMachine;
+use itertools::chain;
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt;
+
+use crate::context::{ExecutionContext, StateAccess};
+use crate::contract::{calldata_arg, contract_address, read_retdata, ContractClass, Contracts};
+use crate::events::{Event, L2ToL1Message};
+use crate::program::CairoRun;
+
+/// The prefix of the cheatcodes overriding a value of the execution info, e.g.
+/// `start_cheat_block_timestamp`.
+const START_CHEAT_PREFIX: &str = "start_cheat_";
+/// The prefix of the cheatcodes restoring a value of the execution info, e.g.
+/// `stop_cheat_block_timestamp`.
+const STOP_CHEAT_PREFIX: &str = "stop_cheat_";
+
+// The gas costs of the contract syscalls, as the runner charges them. Its `gas_costs` module is
+// private.
+const STEP_GAS: usize = 100;
+/// The gas added to the gas left for a call, to run the entry point.
+const ENTRY_POINT_INITIAL_BUDGET_GAS: usize = 100 * STEP_GAS;
+const ENTRY_POINT_GAS: usize = ENTRY_POINT_INITIAL_BUDGET_GAS + 500 * STEP_GAS;
+const CALL_CONTRACT_GAS: usize = 10 * STEP_GAS + ENTRY_POINT_GAS;
+const DEPLOY_GAS: usize = 200 * STEP_GAS + ENTRY_POINT_GAS;
+
+/// The hint processor of [`crate::program::CairoProgram`] runs: the runner's `CairoHintProcessor`,
+/// extended with cheatcodes for tests and mocked contract calls, capturing what the Cairo code
+/// prints instead of writing it to stdout, and recording the events and messages to L1 it emits.
+///
+/// Cairo code calls the cheatcodes with `starknet::testing::cheatcode::<'selector'>(inputs)`, all
+/// returning an empty span:
+///
+/// * `start_cheat_<name>([value])` sets a value of the execution info, until
+///   `stop_cheat_<name>([])` restores its value in the [`ExecutionContext`] of the run. `<name>` is
+///   `block_number`, `block_timestamp`, `sequencer_address`, `caller_address`, `contract_address`,
+///   `transaction_hash` or `chain_id`.
+/// * `mock_call([contract_address, selector, ret_data..])` makes `call_contract_syscall` return
+///   `ret_data` for the entry point `selector` of the contract, without running it nor consuming
+///   its gas, until `stop_mock_call([contract_address, selector])`.
+///
+/// The cheatcodes of `starknet::testing`, such as `set_block_timestamp` or `pop_log`, are executed
+/// by the runner as usual.
+///
+/// In a [`crate::contract::ContractEnv`], `call_contract_syscall`, `library_call_syscall` and
+/// `deploy_syscall` run the contracts and classes of the environment, each in a processor of its
+/// own sharing the state, the mocked calls and the output.
+pub struct CheatcodeHintProcessor<'a> {
+    inner: CairoHintProcessor<'a>,
+    /// The context the run started in.
+    context: &'a ExecutionContext,
+    /// The data returned by the mocked calls, by contract address and entry point selector.
+    mocked_calls: HashMap<(Felt, Felt), Vec<Felt>>,
+    /// What the Cairo code printed so far.
+    output: String,
+    /// Whether to also write what the Cairo code prints to stdout, as it prints it.
+    stream_output: bool,
+    /// The events emitted so far.
+    events: Vec<Event>,
+    /// The messages to L1 sent so far.
+    l2_to_l1_messages: Vec<L2ToL1Message>,
+    /// The contracts the contract syscalls run, if any.
+    contracts: Option<Contracts<'a>>,
+}
+
+impl<'a> CheatcodeHintProcessor<'a> {
+    /// Creates a processor for runs of `runner` in `context`, starting from `starknet_state`, e.g.
+    /// the state of the context. With `stream_output`, what the Cairo code prints is also written
+    /// to stdout as it prints it.
+    pub fn new(
+        runner: &'a SierraCasmRunner,
+        context: &'a ExecutionContext,
+        starknet_state: StarknetState,
+        stream_output: bool,
+    ) -> Self {
+        Self {
+            inner: CairoHintProcessor {
+                runner: Some(runner),
+                string_to_hint: HashMap::new(),
+                starknet_state,
+                run_resources: RunResources::default(),
+                syscalls_used_resources: Default::default(),
+            },
+            context,
+            mocked_calls: context.mocked_calls.clone().into_iter().collect(),
+            output: String::new(),
+            stream_output,
+            events: vec![],
+            l2_to_l1_messages: vec![],
+            contracts: None,
+        }
+    }
+
+    /// Runs the contract syscalls against `contracts`.
+    pub(crate) fn with_contracts(mut self, contracts: Contracts<'a>) -> Self {
+        self.contracts = Some(contracts);
+        self
+    }
+
+    /// Returns the contracts the contract syscalls ran against, with those they deployed.
+    pub(crate) fn into_contracts(self) -> Option<Contracts<'a>> {
+        self.contracts
+    }
+
+    /// Records the felts printed by `print!`, `println!` or `core::debug::print`, formatted the
+    /// way the runner writes them to stdout.
+    fn print(&mut self, felts: Vec<Felt>) {
+        let printed = format_for_debug(felts);
+        if self.stream_output {
+            let mut stdout = std::io::stdout().lock();
+            // Like `print!`, but not panicking if stdout is closed.
+            let _ = stdout.write_all(printed.as_bytes());
+            let _ = stdout.flush();
+        }
+        self.output.push_str(&printed);
+    }
+
+    /// Executes the cheatcode `selector` if it is one of ours, returning whether it was.
+    fn execute_cheatcode(&mut self, selector: &str, inputs: &[Felt]) -> Result<bool, HintError> {
+        let invalid_args = |expected: &str| {
+            HintError::CustomHint(
+                format!(
+                    "`{selector}` cheatcode invalid args: pass span of an array with {expected}"
+                )
+                .into_boxed_str(),
+            )
+        };
+        let execution_info = self.context.execution_info();
+        let context_value = |name: &str| {
+            execution_info
+                .iter()
+                .find(|(info_name, _)| *info_name == name)
+                .map(|(_, value)| *value)
+        };
+
+        if let Some((name, _)) = selector
+            .strip_prefix(START_CHEAT_PREFIX)
+            .and_then(|name| Some((name, context_value(name)?)))
+        {
+            let [value] = inputs else {
+                return Err(invalid_args("exactly one element"));
+            };
+            self.set_execution_info(name, *value)?;
+        } else if let Some((name, value)) = selector
+            .strip_prefix(STOP_CHEAT_PREFIX)
+            .and_then(|name| Some((name, context_value(name)?)))
+        {
+            if !inputs.is_empty() {
+                return Err(invalid_args("no elements"));
+            }
+            self.set_execution_info(name, value)?;
+        } else if selector == "mock_call" {
+            let [contract_address, entry_point, ret_data @ ..] = inputs else {
+                return Err(invalid_args("at least two elements"));
+            };
+            self.mocked_calls
+                .insert((*contract_address, *entry_point), ret_data.to_vec());
+        } else if selector == "stop_mock_call" {
+            let [contract_address, entry_point] = inputs else {
+                return Err(invalid_args("exactly two elements"));
+            };
+            self.mocked_calls.remove(&(*contract_address, *entry_point));
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Sets the value `name` of the execution info, with the `set_<name>` cheatcode of the runner.
+    fn set_execution_info(&mut self, name: &str, value: Felt) -> Result<(), HintError> {
+        let mut access = StateAccess::new(std::mem::take(&mut self.inner.starknet_state));
+        let result = access.cheatcode(&format!("set_{name}"), &[value]);
+        self.inner.starknet_state = access.into_state();
+        result.map_err(|e| HintError::CustomHint(format!("{e:#}").into_boxed_str()))
+    }
+
+    /// Records the event or message to L1 emitted by the syscall executed on `request`, if any
+    /// and if it succeeded.
+    fn record_log(
+        &mut self,
+        request: Relocatable,
+        vm: &mut VirtualMachine,
+    ) -> Result<(), HintError> {
+        // The request: the syscall selector, the gas, then the event keys and data spans, or the
+        // message recipient and payload span. The response follows it: the remaining gas, then a
+        // failure flag.
+        let selector = *vm.get_integer(request)?;
+        let succeeded = |vm: &VirtualMachine, response: Relocatable| -> Result<bool, HintError> {
+            Ok(*vm.get_integer((response + 1)?)? == Felt::ZERO)
+        };
+        let read_span = |vm: &mut VirtualMachine, span: Relocatable| -> Result<_, HintError> {
+            let start = vm.get_relocatable(span)?;
+            let end = vm.get_relocatable((span + 1)?)?;
+            vm_get_range(vm, start, end)
+        };
+
+        if selector == Felt::from_bytes_be_slice(b"EmitEvent") {
+            if succeeded(vm, (request + 6)?)? {
+                let event = Event {
+                    from_address: self.contract_address(),
+                    keys: read_span(vm, (request + 2)?)?,
+                    data: read_span(vm, (request + 4)?)?,
+                };
+                self.events.push(event);
+            }
+        } else if selector == Felt::from_bytes_be_slice(b"SendMessageToL1")
+            && succeeded(vm, (request + 5)?)?
+        {
+            let message = L2ToL1Message {
+                from_address: self.contract_address(),
+                to_address: *vm.get_integer((request + 2)?)?,
+                payload: read_span(vm, (request + 3)?)?,
+            };
+            self.l2_to_l1_messages.push(message);
+        }
+        Ok(())
+    }
+
+    /// Returns the address of the contract being executed.
+    fn contract_address(&mut self) -> Felt {
+        // `StarknetState` only exposes it by replacing it.
+        let state = &mut self.inner.starknet_state;
+        let addresses = state.open_caller_context((Felt::ZERO, Felt::ZERO));
+        state.close_caller_context(addresses);
+        addresses.0
+    }
+
+    /// Answers the syscall of `system` if it calls a mocked entry point, returning whether it did.
+    fn execute_mocked_call(
+        &mut self,
+        system: &ResOperand,
+        vm: &mut VirtualMachine,
+    ) -> Result<bool, HintError> {
+        // The request: the syscall selector, the gas, the contract address, the entry point
+        // selector and the calldata span.
+        let request = extract_relocatable(vm, system)?;
+        if *vm.get_integer(request)? != Felt::from_bytes_be_slice(b"CallContract") {
+            return Ok(false);
+        }
+        let contract_address = *vm.get_integer((request + 2)?)?;
+        let entry_point = *vm.get_integer((request + 3)?)?;
+        let Some(ret_data) = self.mocked_calls.get(&(contract_address, entry_point)) else {
+            return Ok(false);
+        };
+
+        let ret_data = ret_data
+            .iter()
+            .map(MaybeRelocatable::from)
+            .collect::<Vec<_>>();
+        let ret_start = vm.add_memory_segment();
+        let ret_end = vm.load_data(ret_start, &ret_data)?;
+        // The response follows the request: the remaining gas, a failure flag and the returned
+        // span.
+        let gas = *vm.get_integer((request + 1)?)?;
+        vm.load_data(
+            (request + 6)?,
+            &[
+                gas.into(),
+                Felt::ZERO.into(),
+                ret_start.into(),
+                ret_end.into(),
+            ],
+        )?;
+        *self
+            .inner
+            .syscalls_used_resources
+            .syscalls
+            .entry("CallContract".into())
+            .or_default() += 1;
+        Ok(true)
+    }
+
+    /// Executes the syscall of `system` against the contracts of the run if it calls a contract
+    /// or a class, or deploys a contract, returning whether it did.
+    fn execute_contract_syscall(
+        &mut self,
+        system: &ResOperand,
+        vm: &mut VirtualMachine,
+    ) -> Result<bool, HintError> {
+        if self.contracts.is_none() {
+            return Ok(false);
+        }
+        // The request: the syscall selector, the gas, the contract address or class hash, the
+        // entry point selector or deployment salt, the calldata span, and for deployments whether
+        // to deploy from the zero address. The response follows it: the remaining gas, a failure
+        // flag, the deployed contract address for deployments, then the returned span or the
+        // revert reason span.
+        let request = extract_relocatable(vm, system)?;
+        let selector = *vm.get_integer(request)?;
+        let Some(name) = ["CallContract", "LibraryCall", "Deploy"]
+            .into_iter()
+            .find(|name| selector == Felt::from_bytes_be_slice(name.as_bytes()))
+        else {
+            return Ok(false);
+        };
+        let gas = vm
+            .get_integer((request + 1)?)?
+            .to_usize()
+            .unwrap_or(usize::MAX);
+        let target = *vm.get_integer((request + 2)?)?;
+        let entry_point_or_salt = *vm.get_integer((request + 3)?)?;
+        let calldata_start = vm.get_relocatable((request + 4)?)?;
+        let calldata_end = vm.get_relocatable((request + 5)?)?;
+        let calldata = vm_get_range(vm, calldata_start, calldata_end)?;
+        *self
+            .inner
+            .syscalls_used_resources
+            .syscalls
+            .entry(name.into())
+            .or_default() += 1;
+
+        let cost = match name {
+            "Deploy" => DEPLOY_GAS,
+            _ => CALL_CONTRACT_GAS,
+        };
+        let (gas, result) = match gas.checked_sub(cost) {
+            None => (
+                gas,
+                Err(vec![Felt::from_bytes_be_slice(b"Syscall out of gas")]),
+            ),
+            Some(gas) => match name {
+                "CallContract" => self.call_contract(gas, target, entry_point_or_salt, calldata)?,
+                "LibraryCall" => self.library_call(gas, target, entry_point_or_salt, calldata)?,
+                _ => {
+                    let deploy_from_zero = *vm.get_integer((request + 6)?)? != Felt::ZERO;
+                    self.deploy(gas, target, entry_point_or_salt, calldata, deploy_from_zero)?
+                }
+            },
+        };
+
+        let mut response = vec![MaybeRelocatable::from(Felt::from(gas))];
+        let data = match result {
+            Ok((address, retdata)) => {
+                response.push(Felt::ZERO.into());
+                response.extend(address.map(MaybeRelocatable::from));
+                retdata
+            }
+            Err(revert_reason) => {
+                response.push(Felt::ONE.into());
+                revert_reason
+            }
+        };
+        let data = data.iter().map(MaybeRelocatable::from).collect::<Vec<_>>();
+        let data_start = vm.add_memory_segment();
+        let data_end = vm.load_data(data_start, &data)?;
+        response.extend([data_start.into(), data_end.into()]);
+        let response_start = match name {
+            "Deploy" => (request + 7)?,
+            _ => (request + 6)?,
+        };
+        vm.load_data(response_start, &response)?;
+        Ok(true)
+    }
+
+    /// Calls the entry point `selector` of the contract at `address` with `gas`.
+    fn call_contract(
+        &mut self,
+        gas: usize,
+        address: Felt,
+        selector: Felt,
+        calldata: Vec<Felt>,
+    ) -> Result<ContractSyscallResult, HintError> {
+        let contracts = self.contracts.as_ref().expect("contracts are set");
+        let classes = contracts.classes;
+        let Some(class) = contracts.deployed.get(&address).map(|hash| &classes[hash]) else {
+            return Ok((
+                gas,
+                Err(vec![Felt::from_bytes_be_slice(b"CONTRACT_NOT_DEPLOYED")]),
+            ));
+        };
+        let caller_address = self.contract_address();
+        let (gas, result) = self.run_entry_point(
+            gas,
+            class,
+            selector,
+            calldata,
+            Some((address, caller_address)),
+        )?;
+        Ok((gas, with_failure(result, b"ENTRYPOINT_FAILED")))
+    }
+
+    /// Calls the entry point `selector` of the class `class_hash` with `gas`, in the context of the
+    /// calling contract.
+    fn library_call(
+        &mut self,
+        gas: usize,
+        class_hash: Felt,
+        selector: Felt,
+        calldata: Vec<Felt>,
+    ) -> Result<ContractSyscallResult, HintError> {
+        let classes = self.contracts.as_ref().expect("contracts are set").classes;
+        let Some(class) = classes.get(&class_hash) else {
+            return Ok((
+                gas,
+                Err(vec![Felt::from_bytes_be_slice(b"CLASS_HASH_NOT_FOUND")]),
+            ));
+        };
+        let (gas, result) = self.run_entry_point(gas, class, selector, calldata, None)?;
+        Ok((gas, with_failure(result, b"ENTRYPOINT_FAILED")))
+    }
+
+    /// Deploys a contract of the class `class_hash`, running its constructor with `gas`.
+    fn deploy(
+        &mut self,
+        gas: usize,
+        class_hash: Felt,
+        salt: Felt,
+        calldata: Vec<Felt>,
+        deploy_from_zero: bool,
+    ) -> Result<ContractSyscallResult, HintError> {
+        let fail = |reason: &[u8]| Ok((gas, Err(vec![Felt::from_bytes_be_slice(reason)])));
+        let deployer_address = match deploy_from_zero {
+            true => Felt::ZERO,
+            false => self.contract_address(),
+        };
+        let address = contract_address(salt, class_hash, &calldata, deployer_address);
+        let contracts = self.contracts.as_mut().expect("contracts are set");
+        let Some(class) = contracts.classes.get(&class_hash) else {
+            return fail(b"CLASS_HASH_NOT_FOUND");
+        };
+        if class.constructor().is_none() && !calldata.is_empty() {
+            return fail(b"INVALID_CALLDATA_LEN");
+        }
+        // The contract is deployed before its constructor runs, which can call it.
+        if contracts.deployed.insert(address, class_hash).is_some() {
+            return fail(b"CONTRACT_ALREADY_DEPLOYED");
+        }
+        if class.constructor().is_none() {
+            return Ok((gas, Ok((Some(address), vec![]))));
+        }
+
+        let (gas, result) = self.run_entry_point(
+            gas,
+            class,
+            None,
+            calldata,
+            Some((address, deployer_address)),
+        )?;
+        if result.is_err() {
+            let contracts = self.contracts.as_mut().expect("contracts are set");
+            contracts.deployed.remove(&address);
+        }
+        let result = result.map(|(_, retdata)| (Some(address), retdata));
+        Ok((gas, with_failure(result, b"CONSTRUCTOR_FAILED")))
+    }
+
+    /// Runs the external entry point `selector` of `class`, or its constructor without a
+    /// selector, with `gas` left, in a processor of its own. With `addresses`, runs it as the
+    /// contract at the first address called by the second.
+    ///
+    /// The state, the deployed contracts, the events and the messages to L1 of the run are kept
+    /// only if it succeeds.
+    fn run_entry_point(
+        &mut self,
+        gas: usize,
+        class: &'a ContractClass,
+        selector: impl Into<Option<Felt>>,
+        calldata: Vec<Felt>,
+        addresses: Option<(Felt, Felt)>,
+    ) -> Result<ContractSyscallResult, HintError> {
+        let function = match selector.into() {
+            Some(selector) => class.entry_point(selector),
+            None => class.constructor(),
+        };
+        let Some(function) = function else {
+            return Ok((
+                gas,
+                Err(vec![Felt::from_bytes_be_slice(b"ENTRYPOINT_NOT_FOUND")]),
+            ));
+        };
+        let mut state = self.inner.starknet_state.clone();
+        let previous_addresses = addresses.map(|addresses| state.open_caller_context(addresses));
+        let mut processor = CheatcodeHintProcessor::new(
+            class.program().runner(),
+            self.context,
+            state,
+            self.stream_output,
+        );
+        processor.mocked_calls = self.mocked_calls.clone();
+        processor.contracts = self.contracts.clone();
+        let run = run_with_processor(
+            &mut processor,
+            function,
+            &[calldata_arg(&calldata)],
+            Some(gas + ENTRY_POINT_INITIAL_BUDGET_GAS),
+        )
+        .map_err(|e| HintError::CustomHint(format!("{e:#}").into_boxed_str()))?;
+
+        self.output.push_str(&run.output);
+        self.inner.syscalls_used_resources += run.result.used_resources;
+        let gas = run
+            .result
+            .gas_counter
+            .and_then(|gas| gas.to_usize())
+            .unwrap_or_default();
+        let retdata = match run.result.value {
+            RunResultValue::Success(values) => read_retdata(&run.result.memory, &values)
+                .map_err(|e| HintError::CustomHint(format!("{e:#}").into_boxed_str()))?,
+            RunResultValue::Panic(revert_reason) => return Ok((gas, Err(revert_reason))),
+        };
+        let mut state = run.result.starknet_state;
+        if let Some(previous_addresses) = previous_addresses {
+            state.close_caller_context(previous_addresses);
+        }
+        self.inner.starknet_state = state;
+        self.events.extend(run.events);
+        self.l2_to_l1_messages.extend(run.l2_to_l1_messages);
+        self.contracts = processor.contracts;
+        Ok((gas, Ok((None, retdata))))
+    }
+}
+
+/// The gas left after a contract syscall, and its result: the deployed contract address for
+/// deployments and the returned data, or the revert reason.
+type ContractSyscallResult = (usize, Result<(Option<Felt>, Vec<Felt>), Vec<Felt>>);
+
+/// Appends `reason` to the revert reason of a failed contract call, as the runner does.
+fn with_failure<T>(result: Result<T, Vec<Felt>>, reason: &[u8]) -> Result<T, Vec<Felt>> {
+    result.map_err(|mut revert_reason| {
+        revert_reason.push(Felt::from_bytes_be_slice(reason));
+        revert_reason
+    })
+}
+
+impl HintProcessorLogic for CheatcodeHintProcessor<'_> {
+    fn execute_hint(
+        &mut self,
+        vm: &mut VirtualMachine,
+        exec_scopes: &mut ExecutionScopes,
+        hint_data: &Box<dyn Any>,
+        constants: &HashMap<String, Felt>,
+    ) -> Result<(), HintError> {
+        match hint_data.downcast_ref::<Hint>() {
+            Some(Hint::Starknet(StarknetHint::Cheatcode {
+                selector,
+                input_start,
+                input_end,
+                output_start,
+                output_end,
+            })) => {
+                let selector = selector.value.to_bytes_be().1;
+                if let Ok(selector) = std::str::from_utf8(&selector) {
+                    let input_start = extract_relocatable(vm, input_start)?;
+                    let input_end = extract_relocatable(vm, input_end)?;
+                    let inputs = vm_get_range(vm, input_start, input_end)?;
+                    if self.execute_cheatcode(selector, &inputs)? {
+                        let output = vm.add_memory_segment();
+                        vm.insert_value(cell_ref_to_relocatable(output_start, vm), output)?;
+                        vm.insert_value(cell_ref_to_relocatable(output_end, vm), output)?;
+                        return Ok(());
+                    }
+                }
+            }
+            Some(Hint::Starknet(StarknetHint::SystemCall { system })) => {
+                if self.execute_mocked_call(system, vm)?
+                    || self.execute_contract_syscall(system, vm)?
+                {
+                    return Ok(());
+                }
+                let request = extract_relocatable(vm, system)?;
+                self.inner
+                    .execute_hint(vm, exec_scopes, hint_data, constants)?;
+                return self.record_log(request, vm);
+            }
+            Some(Hint::Core(CoreHintBase::Core(CoreHint::DebugPrint { start, end }))) => {
+                let start = extract_relocatable(vm, start)?;
+                let end = extract_relocatable(vm, end)?;
+                let felts = vm_get_range(vm, start, end)?;
+                self.print(felts);
+                return Ok(());
+            }
+            _ => {}
+        }
+        self.inner
+            .execute_hint(vm, exec_scopes, hint_data, constants)
+    }
+
+    fn compile_hint(
+        &self,
+        hint_code: &str,
+        ap_tracking_data: &ApTracking,
+        reference_ids: &HashMap<String, usize>,
+        references: &[HintReference],
+    ) -> Result<Box<dyn Any>, VirtualMachineError> {
+        self.inner
+            .compile_hint(hint_code, ap_tracking_data, reference_ids, references)
+    }
+}
+
+impl ResourceTracker for CheatcodeHintProcessor<'_> {
+    fn consumed(&self) -> bool {
+        self.inner.consumed()
+    }
+
+    fn consume_step(&mut self) {
+        self.inner.consume_step()
+    }
+
+    fn get_n_steps(&self) -> Option<usize> {
+        self.inner.get_n_steps()
+    }
+
+    fn run_resources(&self) -> &RunResources {
+        self.inner.run_resources()
+    }
+}
+
+/// Runs `function` of `runner` starting in `context`, with the cheatcodes of
+/// [`CheatcodeHintProcessor`]. With `stream_output`, what the function prints is also written to
+/// stdout as it prints it.
+pub fn run_with_cheatcodes(
+    runner: &SierraCasmRunner,
+    function: &Function,
+    args: &[Arg],
+    available_gas: Option<usize>,
+    context: &ExecutionContext,
+    stream_output: bool,
+) -> Result<CairoRun> {
+    let mut processor =
+        CheatcodeHintProcessor::new(runner, context, context.starknet_state()?, stream_output);
+    run_with_processor(&mut processor, function, args, available_gas)
+}
+
+/// Runs `function` of the runner of `processor` with it. What the processor collected, such as
+/// the final state or the output, is moved to the run.
+///
+/// Same as `SierraCasmRunner::run_function_with_starknet_context`, which can't be given another
+/// hint processor.
+pub(crate) fn run_with_processor(
+    processor: &mut CheatcodeHintProcessor,
+    function: &Function,
+    args: &[Arg],
+    available_gas: Option<usize>,
+) -> Result<CairoRun> {
+    let runner = processor.inner.runner.expect("the processor has a runner");
+    let initial_gas = runner.get_initial_available_gas(function, available_gas)?;
+    let (entry_code, builtins) = runner.create_entry_code(function, args, initial_gas)?;
+    let footer = SierraCasmRunner::create_code_footer();
+    let casm_program = runner.get_casm_program();
+    let (hints_dict, string_to_hint) =
+        build_hints_dict(chain!(&entry_code, &casm_program.instructions));
+    let assembled_program = casm_program.clone().assemble_ex(&entry_code, &footer);
+
+    processor.inner.string_to_hint = string_to_hint;
+    let RunResult {
+        gas_counter,
+        memory,
+        value,
+        used_resources,
+        profiling_info,
+    } = runner.run_function(
+        function,
+        processor,
+        hints_dict,
+        assembled_program.bytecode.iter(),
+        builtins,
+    )?;
+
+    let mut all_used_resources = std::mem::take(&mut processor.inner.syscalls_used_resources);
+    all_used_resources.basic_resources += &used_resources;
+    // The entry code isn't part of the function: it only sets up the arguments, and calls it.
+    all_used_resources.basic_resources.n_steps -= entry_code.len();
+    Ok(CairoRun {
+        result: RunResultStarknet {
+            gas_counter,
+            memory,
+            value,
+            starknet_state: std::mem::take(&mut processor.inner.starknet_state),
+            used_resources: all_used_resources,
+            profiling_info,
+        },
+        output: std::mem::take(&mut processor.output),
+        events: std::mem::take(&mut processor.events),
+        l2_to_l1_messages: std::mem::take(&mut processor.l2_to_l1_messages),
+    })
+}
+
+/// Formats printed felts like the runner does: `ByteArray`s as text, and other felts as hex with
+/// their short string, each on a `[DEBUG]` line. A single `ByteArray` is formatted as is, which is
+/// what `print!` prints.
+fn format_for_debug(felts: Vec<Felt>) -> String {
+    let mut felts = felts.into_iter();
+    let mut items = Vec::new();
+    while let Some(item) = format_next_item(&mut felts) {
+        // `FormattedItem` doesn't expose whether the item is a string, but only strings are
+        // quoted.
+        let quoted = item.quote_if_string();
+        items.push(
+            match quoted.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                Some(string) => (string.to_string(), true),
+                None => (quoted, false),
+            },
+        );
+    }
+    if let [(string, true)] = items.as_slice() {
+        return string.clone();
+    }
+    items
+        .into_iter()
+        .map(|(item, is_string)| match is_string {
+            true => format!("{item}\n"),
+            false => format!("[DEBUG]\t{item}\n"),
+        })
+        .collect()
+}
@@ -0,0 +1,319 @@
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, bail, Context, Result};
+use cairo_lang_casm::hints::{Hint, StarknetHint};
+use cairo_lang_casm::operand::{CellRef, Register, ResOperand};
+use cairo_lang_runner::{CairoHintProcessor, StarknetState};
+use cairo_lang_utils::bigint::BigIntAsHex;
+use cairo_vm::hint_processor::hint_processor_definition::HintProcessorLogic;
+use cairo_vm::types::exec_scope::ExecutionScopes;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+use cairo_vm::vm::runners::cairo_runner::RunResources;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt;
+
+/// The gas given to the syscalls executed on a state outside of a run, enough for any of them.
+const SYSCALL_GAS: u64 = 1_000_000;
+
+/// The Starknet context a Cairo function is called in: the execution info read by syscalls such
+/// as `get_block_number` or `get_caller_address`, and the initial contract storage.
+///
+/// Every value is zero and the storage is empty by default, as in `StarknetState::default()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionContext {
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    pub sequencer_address: Felt,
+    pub caller_address: Felt,
+    pub contract_address: Felt,
+    pub transaction_hash: Felt,
+    pub chain_id: Felt,
+    /// The initial storage values, by contract address and storage address.
+    pub storage: BTreeMap<(Felt, Felt), Felt>,
+    /// The data returned by calls to contracts instead of running them, by contract address and
+    /// entry point selector. See [`crate::cheatcodes::CheatcodeHintProcessor`] to mock calls from
+    /// Cairo.
+    pub mocked_calls: BTreeMap<(Felt, Felt), Vec<Felt>>,
+}
+
+impl ExecutionContext {
+    pub fn with_block_number(mut self, block_number: u64) -> Self {
+        self.block_number = block_number;
+        self
+    }
+
+    pub fn with_block_timestamp(mut self, block_timestamp: u64) -> Self {
+        self.block_timestamp = block_timestamp;
+        self
+    }
+
+    pub fn with_sequencer_address(mut self, sequencer_address: Felt) -> Self {
+        self.sequencer_address = sequencer_address;
+        self
+    }
+
+    pub fn with_caller_address(mut self, caller_address: Felt) -> Self {
+        self.caller_address = caller_address;
+        self
+    }
+
+    pub fn with_contract_address(mut self, contract_address: Felt) -> Self {
+        self.contract_address = contract_address;
+        self
+    }
+
+    pub fn with_transaction_hash(mut self, transaction_hash: Felt) -> Self {
+        self.transaction_hash = transaction_hash;
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: Felt) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Sets the value at storage address `key` of the contract at `contract_address`.
+    pub fn with_storage(mut self, contract_address: Felt, key: Felt, value: Felt) -> Self {
+        self.storage.insert((contract_address, key), value);
+        self
+    }
+
+    /// Makes calls to the entry point `selector` of the contract at `contract_address` return
+    /// `ret_data`.
+    pub fn with_mocked_call(
+        mut self,
+        contract_address: Felt,
+        selector: Felt,
+        ret_data: Vec<Felt>,
+    ) -> Self {
+        self.mocked_calls
+            .insert((contract_address, selector), ret_data);
+        self
+    }
+
+    /// Returns the values of the execution info, by the name of the cheatcode setting them
+    /// without its `set_` prefix, e.g. `block_number`.
+    pub(crate) fn execution_info(&self) -> [(&'static str, Felt); 7] {
+        [
+            ("block_number", Felt::from(self.block_number)),
+            ("block_timestamp", Felt::from(self.block_timestamp)),
+            ("sequencer_address", self.sequencer_address),
+            ("caller_address", self.caller_address),
+            ("contract_address", self.contract_address),
+            ("transaction_hash", self.transaction_hash),
+            ("chain_id", self.chain_id),
+        ]
+    }
+
+    /// Builds the Starknet state a run starts from.
+    pub fn starknet_state(&self) -> Result<StarknetState> {
+        let mut access = StateAccess::new(StarknetState::default());
+        for ((contract_address, key), value) in &self.storage {
+            access.with_contract(*contract_address, |access| {
+                access.syscall("StorageWrite", &[Felt::ZERO, *key, *value], 0)
+            })?;
+        }
+        for (name, value) in self.execution_info() {
+            access.cheatcode(&format!("set_{name}"), &[value])?;
+        }
+        Ok(access.into_state())
+    }
+
+    /// Reads the execution info of `state`, e.g. the final state of a run. The storage is left
+    /// empty, as the state can't list its storage values, see [`read_storage`], and so are the
+    /// mocked calls.
+    pub fn from_state(state: &StarknetState) -> Result<Self> {
+        let mut access = StateAccess::new(state.clone());
+        let execution_info = match access.syscall("GetExecutionInfo", &[], 1)?.as_slice() {
+            [MaybeRelocatable::RelocatableValue(ptr)] => *ptr,
+            values => bail!("unexpected execution info: {values:?}"),
+        };
+        // See `ExecutionInfo`, `BlockInfo` and `TxInfo` in the `starknet::info` corelib module.
+        let block_info = access.read_pointer(execution_info)?;
+        let tx_info = access.read_pointer((execution_info + 1)?)?;
+        Ok(Self {
+            block_number: access.read_u64(block_info)?,
+            block_timestamp: access.read_u64((block_info + 1)?)?,
+            sequencer_address: access.read_felt((block_info + 2)?)?,
+            caller_address: access.read_felt((execution_info + 2)?)?,
+            contract_address: access.read_felt((execution_info + 3)?)?,
+            transaction_hash: access.read_felt((tx_info + 5)?)?,
+            chain_id: access.read_felt((tx_info + 6)?)?,
+            ..Default::default()
+        })
+    }
+}
+
+/// Returns the value at storage address `key` of the contract at `contract_address` in `state`,
+/// e.g. the final state of a run.
+pub fn read_storage(state: &StarknetState, contract_address: Felt, key: Felt) -> Result<Felt> {
+    let mut access = StateAccess::new(state.clone());
+    let values = access.with_contract(contract_address, |access| {
+        access.syscall("StorageRead", &[Felt::ZERO, key], 1)
+    })?;
+    match values.as_slice() {
+        [MaybeRelocatable::Int(value)] => Ok(*value),
+        values => bail!("unexpected storage value: {values:?}"),
+    }
+}
+
+/// Executes Starknet hints on a state, outside of any run.
+///
+/// `StarknetState` only exposes the contract and caller addresses, so the rest of it is set and
+/// read the way Cairo code does it: through the cheatcodes and syscalls of the runner's hint
+/// processor, with their operands in a scratch VM.
+pub(crate) struct StateAccess {
+    processor: CairoHintProcessor<'static>,
+    vm: VirtualMachine,
+    scopes: ExecutionScopes,
+    /// The offset of the next free cell of the execution segment, where hint operands are.
+    ap: usize,
+}
+
+impl StateAccess {
+    pub(crate) fn new(starknet_state: StarknetState) -> Self {
+        let mut vm = VirtualMachine::new(false);
+        // The program and execution segments, the latter being the one `ap` points to.
+        vm.add_memory_segment();
+        vm.add_memory_segment();
+        Self {
+            processor: CairoHintProcessor {
+                runner: None,
+                string_to_hint: HashMap::new(),
+                starknet_state,
+                run_resources: RunResources::default(),
+                syscalls_used_resources: Default::default(),
+            },
+            vm,
+            scopes: ExecutionScopes::new(),
+            ap: 0,
+        }
+    }
+
+    pub(crate) fn into_state(self) -> StarknetState {
+        self.processor.starknet_state
+    }
+
+    /// Runs `f` with the contract address of the state set to `contract_address`, which storage
+    /// syscalls apply to.
+    fn with_contract<T>(
+        &mut self,
+        contract_address: Felt,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let state = &mut self.processor.starknet_state;
+        let previous = state.open_caller_context((contract_address, Felt::ZERO));
+        let result = f(self);
+        self.processor.starknet_state.close_caller_context(previous);
+        result
+    }
+
+    /// Executes the cheatcode `selector`, e.g. `set_block_number`, with the given inputs.
+    pub(crate) fn cheatcode(&mut self, selector: &str, inputs: &[Felt]) -> Result<()> {
+        let inputs = inputs
+            .iter()
+            .map(MaybeRelocatable::from)
+            .collect::<Vec<_>>();
+        let input_start = self.write_segment(&inputs)?;
+        let input_end = (input_start + inputs.len())?;
+        let [input_start, input_end, output_start, output_end] =
+            self.write_frame(&[input_start.into(), input_end.into()], 2)?;
+        self.execute(StarknetHint::Cheatcode {
+            selector: BigIntAsHex {
+                value: BigInt::from_bytes_be(Sign::Plus, selector.as_bytes()),
+            },
+            input_start: ResOperand::Deref(input_start),
+            input_end: ResOperand::Deref(input_end),
+            output_start,
+            output_end,
+        })
+        .with_context(|| format!("failed to execute the `{selector}` cheatcode"))
+    }
+
+    /// Executes the syscall `selector`, e.g. `StorageRead`, with the given request, and returns
+    /// the `response_len` values of its response.
+    fn syscall(
+        &mut self,
+        selector: &str,
+        request: &[Felt],
+        response_len: usize,
+    ) -> Result<Vec<MaybeRelocatable>> {
+        let mut buffer = vec![
+            Felt::from_bytes_be_slice(selector.as_bytes()).into(),
+            Felt::from(SYSCALL_GAS).into(),
+        ];
+        buffer.extend(request.iter().map(MaybeRelocatable::from));
+        let system = self.write_segment(&buffer)?;
+        let [system_ref] = self.write_frame(&[system.into()], 0)?;
+        self.execute(StarknetHint::SystemCall {
+            system: ResOperand::Deref(system_ref),
+        })
+        .with_context(|| format!("failed to execute the `{selector}` syscall"))?;
+
+        // The response follows the request: the remaining gas, a failure flag, then the values.
+        let response = (system + buffer.len())?;
+        if self.read_felt((response + 1)?)? != Felt::ZERO {
+            bail!("the `{selector}` syscall failed");
+        }
+        self.vm
+            .get_continuous_range((response + 2)?, response_len)
+            .map_err(|e| anyhow!("{e}"))
+    }
+
+    fn execute(&mut self, hint: StarknetHint) -> Result<()> {
+        let hint_data: Box<dyn Any> = Box::new(Hint::Starknet(hint));
+        self.processor
+            .execute_hint(&mut self.vm, &mut self.scopes, &hint_data, &HashMap::new())
+            .map_err(|e| anyhow!("{e}"))
+    }
+
+    /// Writes `values` to a new segment and returns its start.
+    fn write_segment(&mut self, values: &[MaybeRelocatable]) -> Result<Relocatable> {
+        let start = self.vm.add_memory_segment();
+        self.vm
+            .load_data(start, values)
+            .map_err(|e| anyhow!("{e}"))?;
+        Ok(start)
+    }
+
+    /// Points `ap` to fresh cells of the execution segment holding the operands of the next hint:
+    /// `inputs`, followed by `outputs` cells left for the hint to write. Returns the references
+    /// to the `N` cells.
+    fn write_frame<const N: usize>(
+        &mut self,
+        inputs: &[MaybeRelocatable],
+        outputs: usize,
+    ) -> Result<[CellRef; N]> {
+        debug_assert_eq!(inputs.len() + outputs, N);
+        self.vm.set_ap(self.ap);
+        self.vm
+            .load_data(self.vm.get_ap(), inputs)
+            .map_err(|e| anyhow!("{e}"))?;
+        self.ap += N;
+        Ok(std::array::from_fn(|offset| CellRef {
+            register: Register::AP,
+            offset: offset as i16,
+        }))
+    }
+
+    fn read_felt(&self, address: Relocatable) -> Result<Felt> {
+        self.vm
+            .get_integer(address)
+            .map(|value| value.into_owned())
+            .map_err(|e| anyhow!("{e}"))
+    }
+
+    fn read_u64(&self, address: Relocatable) -> Result<u64> {
+        let value = self.read_felt(address)?;
+        value
+            .to_u64()
+            .ok_or_else(|| anyhow!("{value} doesn't fit in a u64"))
+    }
+
+    fn read_pointer(&self, address: Relocatable) -> Result<Relocatable> {
+        self.vm.get_relocatable(address).map_err(|e| anyhow!("{e}"))
+    }
+}
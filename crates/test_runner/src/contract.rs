@@ -0,0 +1,553 @@
+use std::collections::HashMap;
+use std::fs;
+use std::iter::zip;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use cairo_lang_runner::{Arg, RunResultStarknet, RunResultValue, StarknetState};
+use cairo_lang_sierra::ids::UserTypeId;
+use cairo_lang_sierra::program::{Function, GenericArg, Program, ProgramArtifact};
+use cairo_lang_starknet_classes::abi::{EventKind, Input, Item, Output};
+use cairo_lang_starknet_classes::contract_class::{
+    ContractClass as SierraContractClass, ContractEntryPoint,
+};
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::{Felt, NonZeroFelt};
+use starknet_types_core::hash::{Pedersen, Poseidon, StarkHash};
+
+use crate::cheatcodes::{run_with_processor, CheatcodeHintProcessor};
+use crate::context::{read_storage, ExecutionContext};
+use crate::events::{selector, Event};
+use crate::panic::CairoPanic;
+use crate::program::{CairoProgram, CairoRun};
+use crate::value::CairoValue;
+
+/// The name of the constructor in contract ABIs.
+const CONSTRUCTOR: &str = "constructor";
+/// The prefix of the version hashed into class hashes, e.g. `CONTRACT_CLASS_V0.1.0`.
+const CONTRACT_CLASS_VERSION_PREFIX: &str = "CONTRACT_CLASS_V";
+/// `'STARKNET_CONTRACT_ADDRESS'`, hashed into contract addresses.
+const CONTRACT_ADDRESS_PREFIX: Felt =
+    Felt::from_hex_unchecked("0x535441524b4e45545f434f4e54524143545f41444452455353");
+/// `2 ** 251 - 256`, the bound of contract addresses.
+const ADDRESS_BOUND: NonZeroFelt = NonZeroFelt::from_felt_unchecked(Felt::from_hex_unchecked(
+    "0x7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff00",
+));
+
+/// A Starknet contract class, as compiled by Scarb for `starknet-contract` targets to
+/// `target/dev/<package>_<contract>.contract_class.json`.
+pub struct ContractClass {
+    program: CairoProgram,
+    class_hash: Felt,
+    abi: Vec<Item>,
+    /// The index of the constructor function, if any.
+    constructor: Option<usize>,
+    /// The index of the function of each external entry point, by selector.
+    externals: HashMap<Felt, usize>,
+}
+
+impl ContractClass {
+    /// Loads the contract class JSON file at `path` and compiles its Sierra program.
+    pub fn load(path: &Path) -> Result<Self> {
+        let class = fs::read_to_string(path)
+            .with_context(|| format!("failed to read contract class file: {}", path.display()))?;
+        let class: SierraContractClass = serde_json::from_str(&class)
+            .with_context(|| format!("failed to deserialize contract class: {}", path.display()))?;
+        Self::new(class)
+            .with_context(|| format!("failed to load contract class: {}", path.display()))
+    }
+
+    /// Compiles the Sierra program of `class`.
+    pub fn new(class: SierraContractClass) -> Result<Self> {
+        let mut program = class.extract_sierra_program()?;
+        name_user_types(&mut program);
+        let n_functions = program.funcs.len();
+        let function_index = |entry_point: &ContractEntryPoint| {
+            ensure!(
+                entry_point.function_idx < n_functions,
+                "entry point {:#x} has no function",
+                entry_point.selector
+            );
+            Ok((Felt::from(&entry_point.selector), entry_point.function_idx))
+        };
+        let entry_points = &class.entry_points_by_type;
+        let externals = entry_points
+            .external
+            .iter()
+            .map(function_index)
+            .collect::<Result<_>>()?;
+        let constructor = match entry_points.constructor.as_slice() {
+            [] => None,
+            [constructor] => Some(function_index(constructor)?.1),
+            _ => bail!("a contract class has at most one constructor"),
+        };
+        let abi = match &class.abi {
+            // The ABI items are private, but serializable.
+            Some(abi) => serde_json::from_value(serde_json::to_value(abi)?)?,
+            None => vec![],
+        };
+
+        let mut artifact = ProgramArtifact::stripped(program);
+        if let Some(debug_info) = class.sierra_program_debug_info.clone() {
+            artifact = artifact.with_debug_info(debug_info);
+        }
+        let program =
+            CairoProgram::new(artifact, Some(usize::MAX))?.with_member_names(member_names(&abi));
+        Ok(Self {
+            program,
+            class_hash: class_hash(&class)?,
+            abi,
+            constructor,
+            externals,
+        })
+    }
+
+    /// Returns the hash the class is declared with, computed as Starknet does from the class, with
+    /// its ABI serialized as compact JSON.
+    pub fn class_hash(&self) -> Felt {
+        self.class_hash
+    }
+
+    /// Returns the Sierra program of the class, e.g. to decode the events of its contracts.
+    pub fn program(&self) -> &CairoProgram {
+        &self.program
+    }
+
+    /// Returns the function of the external entry point `selector`, if any.
+    pub fn entry_point(&self, selector: Felt) -> Option<&Function> {
+        let index = *self.externals.get(&selector)?;
+        Some(&self.program.artifact().program.funcs[index])
+    }
+
+    /// Returns the constructor function, if any.
+    pub fn constructor(&self) -> Option<&Function> {
+        let index = self.constructor?;
+        Some(&self.program.artifact().program.funcs[index])
+    }
+
+    /// Serializes the arguments of the entry point `entry_point` of the ABI, or of the constructor
+    /// with `"constructor"`, given as a JSON array with one element per parameter.
+    ///
+    /// See [`crate::value::SierraTypes::encode`] for the JSON representation of each type.
+    pub fn encode_calldata(&self, entry_point: &str, args: &str) -> Result<Vec<Felt>> {
+        let args: serde_json::Value = serde_json::from_str(args)
+            .with_context(|| format!("failed to parse arguments: {args}"))?;
+        let serde_json::Value::Array(args) = args else {
+            bail!("arguments must be a JSON array, got: {args}");
+        };
+        if entry_point == CONSTRUCTOR && self.constructor.is_none() {
+            ensure!(args.is_empty(), "the contract has no constructor");
+            return Ok(vec![]);
+        }
+        let (inputs, _) = self.abi_entry_point(entry_point)?;
+        ensure!(
+            args.len() == inputs.len(),
+            "{entry_point} expects {} arguments, got {}",
+            inputs.len(),
+            args.len()
+        );
+
+        let types = self.program.types();
+        let mut calldata = vec![];
+        for (input, arg) in zip(inputs, &args) {
+            let ty = types.find_abi_type(&input.ty)?;
+            calldata.extend(
+                types.serialize(ty, arg).with_context(|| {
+                    format!("invalid argument `{}` of {entry_point}", input.name)
+                })?,
+            );
+        }
+        Ok(calldata)
+    }
+
+    /// Deserializes the data returned by the entry point `entry_point` of the ABI: as a single
+    /// value if it returns one, otherwise as a tuple of its return values.
+    pub fn decode_retdata(&self, entry_point: &str, retdata: &[Felt]) -> Result<CairoValue> {
+        let (_, outputs) = self.abi_entry_point(entry_point)?;
+        let types = self.program.types();
+        let output_types = outputs
+            .iter()
+            .map(|output| types.find_abi_type(&output.ty).cloned())
+            .collect::<Result<Vec<_>>>()?;
+        let mut values = types.deserialize_values(&output_types, retdata)?;
+        Ok(match values.len() {
+            1 => values.remove(0),
+            _ => CairoValue::Tuple(values),
+        })
+    }
+
+    /// Returns the inputs and outputs of the entry point `name` in the ABI.
+    fn abi_entry_point(&self, name: &str) -> Result<(&[Input], &[Output])> {
+        fn find<'a>(items: &'a [Item], name: &str) -> Option<(&'a [Input], &'a [Output])> {
+            items.iter().find_map(|item| match item {
+                Item::Function(function) if function.name == name => {
+                    Some((function.inputs.as_slice(), function.outputs.as_slice()))
+                }
+                Item::L1Handler(handler) if handler.name == name => {
+                    Some((handler.inputs.as_slice(), handler.outputs.as_slice()))
+                }
+                Item::Constructor(constructor) if name == CONSTRUCTOR => {
+                    Some((constructor.inputs.as_slice(), &[]))
+                }
+                Item::Interface(interface) => find(&interface.items, name),
+                _ => None,
+            })
+        }
+        find(&self.abi, name).ok_or_else(|| anyhow!("No entry point named {name} in the ABI"))
+    }
+}
+
+/// The contract classes and deployed contracts that the contract calls and deployments of a run
+/// are executed against.
+#[derive(Clone)]
+pub(crate) struct Contracts<'a> {
+    pub(crate) classes: &'a HashMap<Felt, ContractClass>,
+    /// The class hash of each deployed contract, by address.
+    pub(crate) deployed: HashMap<Felt, Felt>,
+}
+
+/// An in-memory Starknet state to test contracts without a devnet: contract classes are declared
+/// and deployed in it, then their entry points called by name, each call starting from the state
+/// the previous ones left.
+///
+/// The contracts run with the cheatcodes of [`CheatcodeHintProcessor`], and the contracts and
+/// classes they call and deploy are those of the environment.
+pub struct ContractEnv {
+    classes: HashMap<Felt, ContractClass>,
+    /// The class hash of each deployed contract, by address.
+    deployed: HashMap<Felt, Felt>,
+    /// The state left by the previous calls, with the storage of the deployed contracts.
+    state: StarknetState,
+    /// The context of the calls. Contracts are called from its caller address.
+    context: ExecutionContext,
+    /// The gas available to each call.
+    available_gas: usize,
+    /// The number of contracts deployed so far, the salt of the next one.
+    n_deployments: u64,
+}
+
+/// The result of calling an entry point of a contract in a [`ContractEnv`].
+pub struct ContractCall {
+    /// The data returned by the entry point, or nothing if it panicked.
+    pub retdata: Vec<Felt>,
+    /// The raw run, with what was printed and the events and messages to L1 emitted by the
+    /// contract and the contracts it called.
+    pub run: CairoRun,
+}
+
+impl ContractEnv {
+    /// Creates an empty environment starting in `context`, with unlimited gas.
+    pub fn new(context: ExecutionContext) -> Result<Self> {
+        Ok(Self {
+            classes: HashMap::new(),
+            deployed: HashMap::new(),
+            state: context.starknet_state()?,
+            context,
+            available_gas: usize::MAX,
+            n_deployments: 0,
+        })
+    }
+
+    /// Sets the gas available to each call.
+    pub fn with_available_gas(mut self, available_gas: usize) -> Self {
+        self.available_gas = available_gas;
+        self
+    }
+
+    /// Declares `class`, returning its class hash.
+    pub fn declare(&mut self, class: ContractClass) -> Felt {
+        let class_hash = class.class_hash();
+        self.classes.insert(class_hash, class);
+        class_hash
+    }
+
+    /// Returns the declared class `class_hash`.
+    pub fn class(&self, class_hash: Felt) -> Result<&ContractClass> {
+        self.classes
+            .get(&class_hash)
+            .ok_or_else(|| anyhow!("No class declared with hash {class_hash:#x}"))
+    }
+
+    /// Returns the class of the contract deployed at `address`.
+    pub fn class_at(&self, address: Felt) -> Result<&ContractClass> {
+        let class_hash = self
+            .deployed
+            .get(&address)
+            .ok_or_else(|| anyhow!("No contract deployed at {address:#x}"))?;
+        self.class(*class_hash)
+    }
+
+    /// Deploys a contract of the declared class `class_hash`, calling its constructor with the
+    /// arguments given as JSON, see [`ContractClass::encode_calldata`]. Returns the address of the
+    /// contract, or fails with a [`CairoPanic`] if the constructor panicked.
+    pub fn deploy(&mut self, class_hash: Felt, args: &str) -> Result<Felt> {
+        let calldata = self.class(class_hash)?.encode_calldata(CONSTRUCTOR, args)?;
+        self.deploy_raw(class_hash, &calldata)
+    }
+
+    /// Same as [`ContractEnv::deploy`], with serialized constructor arguments.
+    pub fn deploy_raw(&mut self, class_hash: Felt, calldata: &[Felt]) -> Result<Felt> {
+        let has_constructor = self.class(class_hash)?.constructor.is_some();
+        ensure!(
+            has_constructor || calldata.is_empty(),
+            "the contract has no constructor, but was given calldata"
+        );
+        let address = contract_address(
+            Felt::from(self.n_deployments),
+            class_hash,
+            calldata,
+            Felt::ZERO,
+        );
+        self.deployed.insert(address, class_hash);
+        if has_constructor {
+            let call = self.execute(address, None, calldata);
+            if let Err(e) = call.and_then(success) {
+                self.deployed.remove(&address);
+                return Err(e);
+            }
+        }
+        self.n_deployments += 1;
+        Ok(address)
+    }
+
+    /// Calls the entry point `entry_point` of the contract at `address` with the arguments given
+    /// as JSON, and decodes the returned value, see [`ContractClass::decode_retdata`]. Fails with a
+    /// [`CairoPanic`] if the call panicked.
+    pub fn call(&mut self, address: Felt, entry_point: &str, args: &str) -> Result<CairoValue> {
+        let call = success(self.invoke(address, entry_point, args)?)?;
+        self.class_at(address)?
+            .decode_retdata(entry_point, &call.retdata)
+    }
+
+    /// Same as [`ContractEnv::call`], returning the whole call instead of failing if it panicked.
+    pub fn invoke(&mut self, address: Felt, entry_point: &str, args: &str) -> Result<ContractCall> {
+        let calldata = self.class_at(address)?.encode_calldata(entry_point, args)?;
+        self.invoke_raw(address, selector(entry_point), &calldata)
+    }
+
+    /// Calls the external entry point `selector` of the contract at `address` with serialized
+    /// arguments. The state is left as it was if the call panicked.
+    pub fn invoke_raw(
+        &mut self,
+        address: Felt,
+        selector: Felt,
+        calldata: &[Felt],
+    ) -> Result<ContractCall> {
+        self.execute(address, Some(selector), calldata)
+    }
+
+    /// Returns the gas consumed by a call.
+    pub fn gas_consumed(&self, result: &RunResultStarknet) -> Option<u64> {
+        let remaining = u128::try_from(result.gas_counter?).ok()?;
+        u64::try_from((self.available_gas as u128).checked_sub(remaining)?).ok()
+    }
+
+    /// Returns the value at storage address `key` of the contract at `address`.
+    pub fn storage(&self, address: Felt, key: Felt) -> Result<Felt> {
+        read_storage(&self.state, address, key)
+    }
+
+    /// Same as [`CairoProgram::assert_emitted`], for the events of `events` emitted by the
+    /// contract at `address`, decoded with its class.
+    pub fn assert_emitted(
+        &self,
+        address: Felt,
+        events: &[Event],
+        type_name: &str,
+        fields: &str,
+    ) -> Result<()> {
+        let events = events
+            .iter()
+            .filter(|event| event.from_address == address)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.class_at(address)?
+            .program()
+            .assert_emitted(&events, type_name, fields)
+    }
+
+    /// Runs the external entry point `selector` of the contract at `address`, or its constructor
+    /// without a selector, called from the caller address of the context. Keeps the resulting
+    /// state if it succeeded.
+    fn execute(
+        &mut self,
+        address: Felt,
+        selector: Option<Felt>,
+        calldata: &[Felt],
+    ) -> Result<ContractCall> {
+        let class = self.class_at(address)?;
+        let function = match selector {
+            Some(selector) => class
+                .entry_point(selector)
+                .ok_or_else(|| anyhow!("No entry point {selector:#x} in contract {address:#x}"))?,
+            None => class
+                .constructor()
+                .ok_or_else(|| anyhow!("No constructor in contract {address:#x}"))?,
+        };
+        let runner = class.program().runner();
+        let mut state = self.state.clone();
+        let previous = state.open_caller_context((address, self.context.caller_address));
+        let mut processor = CheatcodeHintProcessor::new(runner, &self.context, state, false)
+            .with_contracts(Contracts {
+                classes: &self.classes,
+                deployed: self.deployed.clone(),
+            });
+        let mut run = run_with_processor(
+            &mut processor,
+            function,
+            &[calldata_arg(calldata)],
+            Some(self.available_gas),
+        )?;
+        let deployed = processor
+            .into_contracts()
+            .map(|contracts| contracts.deployed);
+
+        let RunResultValue::Success(values) = &run.result.value else {
+            return Ok(ContractCall {
+                retdata: vec![],
+                run,
+            });
+        };
+        let retdata = read_retdata(&run.result.memory, values)?;
+        let mut state = run.result.starknet_state.clone();
+        state.close_caller_context(previous);
+        self.state = state;
+        if let Some(deployed) = deployed {
+            self.deployed = deployed;
+        }
+        run.result.starknet_state = self.state.clone();
+        Ok(ContractCall { retdata, run })
+    }
+}
+
+/// Fails with a [`CairoPanic`] if `call` panicked.
+fn success(call: ContractCall) -> Result<ContractCall> {
+    match &call.run.result.value {
+        RunResultValue::Success(_) => Ok(call),
+        RunResultValue::Panic(values) => Err(CairoPanic::new(values.clone())
+            .with_output(call.run.output)
+            .into()),
+    }
+}
+
+/// Returns the argument of entry point functions: their serialized arguments, as a span.
+pub(crate) fn calldata_arg(calldata: &[Felt]) -> Arg {
+    Arg::Array(calldata.iter().copied().map(Arg::Value).collect())
+}
+
+/// Reads the span returned by a successful run of an entry point function from the run `memory`,
+/// given the `values` it returned: the addresses of the span start and end.
+pub(crate) fn read_retdata(memory: &[Option<Felt>], values: &[Felt]) -> Result<Vec<Felt>> {
+    let [start, end] = values else {
+        bail!("unexpected entry point return values: {values:?}");
+    };
+    let (Some(start), Some(end)) = (start.to_usize(), end.to_usize()) else {
+        bail!("invalid returned span: [{start}, {end})");
+    };
+    memory
+        .get(start..end)
+        .ok_or_else(|| anyhow!("invalid returned span: [{start}, {end})"))?
+        .iter()
+        .map(|felt| felt.ok_or_else(|| anyhow!("uninitialized returned data")))
+        .collect()
+}
+
+/// Computes the address of a contract deployed by `deployer_address`, as Starknet does.
+pub(crate) fn contract_address(
+    salt: Felt,
+    class_hash: Felt,
+    calldata: &[Felt],
+    deployer_address: Felt,
+) -> Felt {
+    Pedersen::hash_array(&[
+        CONTRACT_ADDRESS_PREFIX,
+        deployer_address,
+        salt,
+        class_hash,
+        Pedersen::hash_array(calldata),
+    ])
+    .mod_floor(&ADDRESS_BOUND)
+}
+
+/// Names the user types of the structs and enums of `program` after their types, the way the
+/// compiler names them: `extract_sierra_program` only names the types, while the runner relies on
+/// the user type names to find panics.
+fn name_user_types(program: &mut Program) {
+    for declaration in &mut program.type_declarations {
+        let Some(name) = &declaration.id.debug_name else {
+            continue;
+        };
+        let Some(GenericArg::UserType(user_type)) = declaration.long_id.generic_args.first_mut()
+        else {
+            continue;
+        };
+        // Tuples are all named `Tuple`.
+        let generic_name = name.split('<').next().unwrap_or(name);
+        user_type.debug_name = [name.as_str(), generic_name]
+            .into_iter()
+            .find(|name| UserTypeId::from_string(*name).id == user_type.id)
+            .map(Into::into);
+    }
+}
+
+/// Returns the member names of the structs and struct events of `abi`, by type name.
+fn member_names(abi: &[Item]) -> HashMap<String, Vec<String>> {
+    abi.iter()
+        .filter_map(|item| match item {
+            Item::Struct(item) => Some((
+                item.name.clone(),
+                item.members
+                    .iter()
+                    .map(|member| member.name.clone())
+                    .collect(),
+            )),
+            Item::Event(item) => match &item.kind {
+                EventKind::Struct { members } => Some((
+                    item.name.clone(),
+                    members.iter().map(|member| member.name.clone()).collect(),
+                )),
+                EventKind::Enum { .. } => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Computes the hash of a Sierra contract class, as Starknet does.
+fn class_hash(class: &SierraContractClass) -> Result<Felt> {
+    let entry_points_hash = |entry_points: &[ContractEntryPoint]| {
+        let felts = entry_points
+            .iter()
+            .flat_map(|entry_point| {
+                [
+                    Felt::from(&entry_point.selector),
+                    Felt::from(entry_point.function_idx),
+                ]
+            })
+            .collect::<Vec<_>>();
+        Poseidon::hash_array(&felts)
+    };
+    let abi = match &class.abi {
+        Some(abi) => serde_json::to_string(abi)?,
+        None => String::new(),
+    };
+    let program = class
+        .sierra_program
+        .iter()
+        .map(|felt| Felt::from(&felt.value))
+        .collect::<Vec<_>>();
+    let version = format!(
+        "{CONTRACT_CLASS_VERSION_PREFIX}{}",
+        class.contract_class_version
+    );
+    let entry_points = &class.entry_points_by_type;
+    Ok(Poseidon::hash_array(&[
+        Felt::from_bytes_be_slice(version.as_bytes()),
+        entry_points_hash(&entry_points.external),
+        entry_points_hash(&entry_points.l1_handler),
+        entry_points_hash(&entry_points.constructor),
+        // The Keccak hash of the ABI, like selectors.
+        selector(&abi),
+        Poseidon::hash_array(&program),
+    ]))
+}
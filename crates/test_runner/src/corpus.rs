@@ -0,0 +1,164 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::gas::write_table;
+use crate::panic::CairoPanic;
+use crate::program::CairoProgram;
+use crate::value::CairoValue;
+
+/// Failing inputs of Cairo functions found by generated tests, stored as concrete arguments.
+///
+/// Unlike proptest's regression seeds, the stored cases don't depend on the strategies that
+/// generated them, so they are replayed as is when strategies change. The file is a JSON array of
+/// [`CorpusCase`]s, meant to be checked into the repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FailureCorpus {
+    cases: Vec<CorpusCase>,
+}
+
+/// A failing call of a Cairo function.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusCase {
+    /// The fully qualified name of the function.
+    pub function: String,
+    /// The hash of the Sierra program the failure was found in, see
+    /// [`CairoProgram::program_hash`].
+    pub program_hash: String,
+    /// The arguments, as a JSON array accepted by [`CairoProgram::call`].
+    pub args: serde_json::Value,
+    /// The output of the function when the failure was found, see [`observed_output`].
+    pub result: String,
+}
+
+impl FailureCorpus {
+    /// Loads the corpus file at `path`, or returns an empty corpus if it doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read failure corpus: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse failure corpus: {}", path.display()))
+    }
+
+    /// Writes the corpus to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content + "\n")
+            .with_context(|| format!("failed to write failure corpus: {}", path.display()))
+    }
+
+    pub fn cases(&self) -> &[CorpusCase] {
+        &self.cases
+    }
+
+    /// Returns the cases of the function named `function`, fully qualified.
+    pub fn cases_for<'a>(&'a self, function: &'a str) -> impl Iterator<Item = &'a CorpusCase> {
+        self.cases
+            .iter()
+            .filter(move |case| case.function == function)
+    }
+
+    /// Adds `case`, replacing the case of the same call if any.
+    pub fn record(&mut self, case: CorpusCase) {
+        match self
+            .cases
+            .iter_mut()
+            .find(|stored| stored.function == case.function && stored.args == case.args)
+        {
+            Some(stored) => *stored = case,
+            None => self.cases.push(case),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cases.is_empty()
+    }
+}
+
+/// Formats the output of a call on a single line, as stored in [`CorpusCase::result`]: the
+/// returned value, or the panic or error of the call.
+pub fn observed_output(output: &Result<CairoValue>) -> String {
+    match output {
+        Ok(value) => value.to_string(),
+        Err(error) => match error.downcast_ref::<CairoPanic>() {
+            Some(panic) => format!("panicked with {}", panic.reason()),
+            None => format!("failed: {error:#}"),
+        },
+    }
+}
+
+/// A corpus case run again against the current program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayedCase {
+    pub case: CorpusCase,
+    /// The output of the current program, formatted like [`CorpusCase::result`].
+    pub result: String,
+    /// Whether the program changed since the failure was found.
+    pub program_changed: bool,
+}
+
+impl ReplayedCase {
+    /// Returns whether the current program returns the output recorded with the failure.
+    pub fn reproduces(&self) -> bool {
+        self.result == self.case.result
+    }
+}
+
+/// The cases of a corpus run again, displayed as a table with one row per case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayReport(pub Vec<ReplayedCase>);
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No failure cases to replay.");
+        }
+
+        let rows = self
+            .0
+            .iter()
+            .map(|replayed| {
+                let status = match (replayed.reproduces(), replayed.program_changed) {
+                    (true, _) => "",
+                    (false, true) => " (changed, program updated)",
+                    (false, false) => " (changed)",
+                };
+                [
+                    replayed.case.function.clone(),
+                    replayed.case.args.to_string(),
+                    replayed.case.result.clone(),
+                    format!("{}{status}", replayed.result),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        write_table(f, ["Function", "Arguments", "Recorded", "Current"], &rows)
+    }
+}
+
+impl CairoProgram {
+    /// Calls the function of every case of `corpus` with its recorded arguments.
+    pub fn replay_corpus(&self, corpus: &FailureCorpus) -> ReplayReport {
+        let program_hash = self.program_hash();
+        ReplayReport(
+            corpus
+                .cases()
+                .iter()
+                .map(|case| ReplayedCase {
+                    result: observed_output(
+                        &self.call_value(&case.function, &case.args.to_string()),
+                    ),
+                    program_changed: case.program_hash != program_hash,
+                    case: case.clone(),
+                })
+                .collect(),
+        )
+    }
+}
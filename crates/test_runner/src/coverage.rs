@@ -0,0 +1,192 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use cairo_lang_runner::profiling::ProfilingInfo;
+use cairo_lang_sierra::program::ProgramArtifact;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+/// The annotation namespace under which the compiler stores the Cairo code locations of Sierra
+/// statements, when `unstable-add-statements-code-locations-debug-info` is enabled.
+const CODE_LOCATIONS_NAMESPACE: &str = "github.com/software-mansion/cairo-coverage";
+const CODE_LOCATIONS_KEY: &str = "statements_code_locations";
+
+/// The number of VM steps executed per Sierra statement, accumulated across runs.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    statement_steps: HashMap<usize, usize>,
+}
+
+impl Coverage {
+    /// Adds the statements executed by a run, from its profiling info.
+    pub fn record(&mut self, profiling_info: &ProfilingInfo) {
+        for (statement, steps) in profiling_info.sierra_statement_weights.iter_sorted() {
+            *self.statement_steps.entry(statement.0).or_default() += steps;
+        }
+    }
+
+    /// Maps the executed statements to the Cairo source lines they were generated from.
+    ///
+    /// Requires the statements code locations debug info, enabled with
+    /// `unstable-add-statements-code-locations-debug-info = true` in the `[cairo]` section of
+    /// `Scarb.toml`.
+    pub fn report(&self, artifact: &ProgramArtifact) -> Result<CoverageReport> {
+        let locations = statements_code_locations(artifact)?;
+
+        let mut files: BTreeMap<String, BTreeMap<usize, usize>> = BTreeMap::new();
+        for (statement, statement_locations) in locations {
+            let steps = self
+                .statement_steps
+                .get(&statement)
+                .copied()
+                .unwrap_or_default();
+            for CodeLocation(file, span, ..) in statement_locations {
+                let lines = files.entry(file).or_default();
+                // Lines are 0-based in the debug info, and 1-based in reports.
+                for line in span.start.line..=span.end.line {
+                    *lines.entry(line + 1).or_default() += steps;
+                }
+            }
+        }
+        Ok(CoverageReport { files })
+    }
+}
+
+/// The Cairo source lines generating executed code, with the number of VM steps executed for
+/// each line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    files: BTreeMap<String, BTreeMap<usize, usize>>,
+}
+
+impl CoverageReport {
+    /// Returns the steps executed per line of `file`, for lines generating code.
+    pub fn file(&self, file: &str) -> Option<&BTreeMap<usize, usize>> {
+        self.files.get(file)
+    }
+
+    /// Keeps only the files under `root`, e.g. to leave the core library out of the report.
+    pub fn retain_under(&mut self, root: &Path) {
+        self.files
+            .retain(|file, _| Path::new(file).starts_with(root));
+    }
+
+    /// Adds the lines of `other`, e.g. from another program of the same package.
+    pub fn merge(&mut self, other: CoverageReport) {
+        for (file, lines) in other.files {
+            let file_lines = self.files.entry(file).or_default();
+            for (line, steps) in lines {
+                *file_lines.entry(line).or_default() += steps;
+            }
+        }
+    }
+
+    /// Formats the report as an LCOV tracefile.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (file, lines) in &self.files {
+            lcov.push_str(&format!("SF:{file}\n"));
+            for (line, steps) in lines {
+                lcov.push_str(&format!("DA:{line},{steps}\n"));
+            }
+            lcov.push_str(&format!("LF:{}\n", lines.len()));
+            lcov.push_str(&format!("LH:{}\n", hit_lines(lines)));
+            lcov.push_str("end_of_record\n");
+        }
+        lcov
+    }
+
+    /// Writes the report to `path` as an LCOV tracefile.
+    pub fn write_lcov(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_lcov())
+            .with_context(|| format!("failed to write LCOV report: {}", path.display()))
+    }
+}
+
+/// Displays the line coverage of each file.
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.files.is_empty() {
+            return write!(f, "No Cairo source lines to cover.");
+        }
+
+        let width = self.files.keys().map(String::len).max().unwrap_or_default();
+        let (mut total_hit, mut total) = (0, 0);
+        for (file, lines) in &self.files {
+            let hit = hit_lines(lines);
+            writeln!(f, "{file:width$}  {}", coverage_line(hit, lines.len()))?;
+            total_hit += hit;
+            total += lines.len();
+        }
+        write!(f, "{:width$}  {}", "Total", coverage_line(total_hit, total))
+    }
+}
+
+fn hit_lines(lines: &BTreeMap<usize, usize>) -> usize {
+    lines.values().filter(|steps| **steps > 0).count()
+}
+
+fn coverage_line(hit: usize, total: usize) -> String {
+    let percent = if total == 0 {
+        100.0
+    } else {
+        hit as f64 * 100.0 / total as f64
+    };
+    format!("{hit:>5}/{total:<5} {percent:>6.2}%")
+}
+
+/// A Cairo code location, as serialized by the compiler: the full path of a source file and a
+/// span in it.
+#[derive(Deserialize)]
+struct CodeLocation(
+    String,
+    CodeSpan,
+    // Later compiler versions append whether the location is in generated code.
+    #[serde(default)]
+    #[allow(dead_code)]
+    Option<IgnoredAny>,
+);
+
+#[derive(Deserialize)]
+struct CodeSpan {
+    start: CodePosition,
+    end: CodePosition,
+}
+
+#[derive(Deserialize)]
+struct CodePosition {
+    line: usize,
+}
+
+/// Returns the Cairo code locations of each Sierra statement of `artifact`, by statement index.
+fn statements_code_locations(
+    artifact: &ProgramArtifact,
+) -> Result<HashMap<usize, Vec<CodeLocation>>> {
+    let locations = artifact
+        .debug_info
+        .as_ref()
+        .and_then(|debug_info| debug_info.annotations.get(CODE_LOCATIONS_NAMESPACE))
+        .and_then(|annotation| annotation.get(CODE_LOCATIONS_KEY))
+        .ok_or_else(|| {
+            anyhow!(
+                "the Sierra program has no statements code locations, set \
+                 `unstable-add-statements-code-locations-debug-info = true` in the `[cairo]` \
+                 section of Scarb.toml and rebuild it"
+            )
+        })?;
+    let locations: HashMap<String, Vec<CodeLocation>> =
+        serde_json::from_value(locations.clone())
+            .context("failed to parse the statements code locations")?;
+    locations
+        .into_iter()
+        .map(|(statement, locations)| {
+            let statement = statement
+                .parse()
+                .with_context(|| format!("invalid statement index: {statement}"))?;
+            Ok((statement, locations))
+        })
+        .collect()
+}
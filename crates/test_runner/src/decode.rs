@@ -0,0 +1,288 @@
+use std::any::type_name;
+
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt;
+use thiserror::Error;
+
+pub use test_runner_macros::CairoDecode;
+
+use crate::value::{byte_array_string, variant_index};
+
+#[derive(Error, Debug)]
+pub enum DecodeError {
+    #[error("not enough felts to decode the value")]
+    NotEnoughFelts,
+    #[error("{0} felts left after decoding the value")]
+    TrailingFelts(usize),
+    #[error("{value} is out of range for {type_name}")]
+    OutOfRange {
+        value: Felt,
+        type_name: &'static str,
+    },
+    #[error("invalid variant selector {selector} for {type_name}")]
+    InvalidVariant {
+        selector: Felt,
+        type_name: &'static str,
+    },
+    #[error("invalid memory address: {0}")]
+    InvalidAddress(Felt),
+    #[error("memory range {0}..{1} out of bounds")]
+    MemoryOutOfBounds(usize, usize),
+    #[error("uninitialized memory cell at {0}")]
+    UninitializedMemory(usize),
+    #[error("invalid ByteArray")]
+    InvalidByteArray,
+    /// A decoded value that doesn't satisfy the invariants of the Rust type.
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
+}
+
+/// A Rust type that can be decoded from the felts of a Cairo value.
+///
+/// Values are laid out as returned by the runner: struct members and tuple elements one after
+/// the other, enums as a variant selector followed by the variant's value, and arrays as a pair
+/// of pointers into the run memory. Implement it for a Rust mirror of a Cairo struct or enum with
+/// `#[derive(CairoDecode)]`.
+pub trait CairoDecode: Sized {
+    /// Returns the size of a value of this type, in felts.
+    fn size() -> usize;
+
+    /// Decodes a value of this type from the start of `reader`, advancing past it.
+    fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError>;
+}
+
+/// Decodes a `T` from all of `felts`, reading arrays from the run `memory`.
+pub fn decode_felts<T: CairoDecode>(
+    felts: Vec<Felt>,
+    memory: &[Option<Felt>],
+) -> Result<T, DecodeError> {
+    let mut reader = FeltReader::new(felts, memory);
+    let value = reader.read()?;
+    match reader.remaining() {
+        0 => Ok(value),
+        remaining => Err(DecodeError::TrailingFelts(remaining)),
+    }
+}
+
+/// A cursor over the felts of a Cairo value, with the memory its arrays point into.
+pub struct FeltReader<'a> {
+    felts: Vec<Felt>,
+    position: usize,
+    memory: &'a [Option<Felt>],
+}
+
+impl<'a> FeltReader<'a> {
+    pub fn new(felts: Vec<Felt>, memory: &'a [Option<Felt>]) -> Self {
+        Self {
+            felts,
+            position: 0,
+            memory,
+        }
+    }
+
+    /// Returns the number of felts left to read.
+    pub fn remaining(&self) -> usize {
+        self.felts.len() - self.position
+    }
+
+    /// Reads the next felt.
+    pub fn next_felt(&mut self) -> Result<Felt, DecodeError> {
+        let felt = *self
+            .felts
+            .get(self.position)
+            .ok_or(DecodeError::NotEnoughFelts)?;
+        self.position += 1;
+        Ok(felt)
+    }
+
+    /// Decodes the next value.
+    pub fn read<T: CairoDecode>(&mut self) -> Result<T, DecodeError> {
+        T::decode(self)
+    }
+
+    /// Skips the next `n` felts.
+    pub fn skip(&mut self, n: usize) -> Result<(), DecodeError> {
+        if n > self.remaining() {
+            return Err(DecodeError::NotEnoughFelts);
+        }
+        self.position += n;
+        Ok(())
+    }
+
+    /// Reads the selector of an enum with `n_variants` variants, returning the variant index.
+    pub fn read_variant(
+        &mut self,
+        n_variants: usize,
+        type_name: &'static str,
+    ) -> Result<usize, DecodeError> {
+        let selector = self.next_felt()?;
+        selector
+            .to_usize()
+            .and_then(|selector| variant_index(n_variants, selector))
+            .ok_or(DecodeError::InvalidVariant {
+                selector,
+                type_name,
+            })
+    }
+
+    /// Reads the start and end pointers of an array, returning a reader over its elements.
+    pub fn read_array(&mut self) -> Result<FeltReader<'a>, DecodeError> {
+        let start = self.read_address()?;
+        let end = self.read_address()?;
+        let felts = self
+            .memory
+            .get(start..end)
+            .ok_or(DecodeError::MemoryOutOfBounds(start, end))?
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| cell.ok_or(DecodeError::UninitializedMemory(start + i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(FeltReader::new(felts, self.memory))
+    }
+
+    fn read_address(&mut self) -> Result<usize, DecodeError> {
+        let address = self.next_felt()?;
+        address
+            .to_usize()
+            .ok_or(DecodeError::InvalidAddress(address))
+    }
+}
+
+impl CairoDecode for Felt {
+    fn size() -> usize {
+        1
+    }
+
+    fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+        reader.next_felt()
+    }
+}
+
+macro_rules! impl_cairo_decode_for_integers {
+    ($($t:ty),+) => {
+        $(
+            impl CairoDecode for $t {
+                fn size() -> usize {
+                    1
+                }
+
+                fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+                    let value = reader.next_felt()?;
+                    value.try_into().map_err(|_| DecodeError::OutOfRange {
+                        value,
+                        type_name: type_name::<$t>(),
+                    })
+                }
+            }
+        )+
+    };
+}
+
+impl_cairo_decode_for_integers!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128);
+
+impl CairoDecode for bool {
+    fn size() -> usize {
+        1
+    }
+
+    fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+        Ok(reader.read_variant(2, "bool")? == 1)
+    }
+}
+
+impl<T: CairoDecode> CairoDecode for Option<T> {
+    fn size() -> usize {
+        1 + T::size()
+    }
+
+    fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+        match reader.read_variant(2, type_name::<Self>())? {
+            0 => Ok(Some(reader.read()?)),
+            _ => {
+                reader.skip(T::size())?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// `Array<T>` and `Span<T>`.
+impl<T: CairoDecode> CairoDecode for Vec<T> {
+    fn size() -> usize {
+        2
+    }
+
+    fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+        let mut elements = reader.read_array()?;
+        let mut values = vec![];
+        while elements.remaining() > 0 {
+            values.push(elements.read()?);
+        }
+        Ok(values)
+    }
+}
+
+/// Fixed-size arrays, laid out inline.
+impl<T: CairoDecode, const N: usize> CairoDecode for [T; N] {
+    fn size() -> usize {
+        N * T::size()
+    }
+
+    fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+        let values = (0..N)
+            .map(|_| reader.read())
+            .collect::<Result<Vec<T>, _>>()?;
+        Ok(values
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("exactly N values were decoded")))
+    }
+}
+
+/// `ByteArray`.
+impl CairoDecode for String {
+    fn size() -> usize {
+        4
+    }
+
+    fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+        let words: Vec<Felt> = reader.read()?;
+        let pending_word: Felt = reader.read()?;
+        let pending_len: usize = reader.read()?;
+        byte_array_string(&words, &pending_word, pending_len).ok_or(DecodeError::InvalidByteArray)
+    }
+}
+
+macro_rules! impl_cairo_decode_for_tuples {
+    ($(($($t:ident),+)),+) => {
+        $(
+            impl<$($t: CairoDecode),+> CairoDecode for ($($t,)+) {
+                fn size() -> usize {
+                    0 $(+ $t::size())+
+                }
+
+                fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+                    Ok(($(reader.read::<$t>()?,)+))
+                }
+            }
+        )+
+    };
+}
+
+impl CairoDecode for () {
+    fn size() -> usize {
+        0
+    }
+
+    fn decode(_reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+impl_cairo_decode_for_tuples!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F)
+);
@@ -0,0 +1,203 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use itertools::{EitherOrBoth, Itertools};
+use proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
+
+use crate::corpus::{observed_output, CorpusCase, FailureCorpus};
+use crate::program::CairoProgram;
+use crate::strategy::StrategyConfig;
+use crate::value::CairoValue;
+
+/// Settings of [`CairoProgram::differential_test`].
+#[derive(Debug, Clone)]
+pub struct DifferentialConfig {
+    /// The number of generated inputs to compare the function and its reference on.
+    pub cases: u32,
+    /// Bounds of the generated inputs.
+    pub strategy: StrategyConfig,
+    /// The failure corpus file to replay before generating inputs, and to record the failing
+    /// input in.
+    pub corpus: Option<PathBuf>,
+}
+
+impl Default for DifferentialConfig {
+    fn default() -> Self {
+        Self {
+            cases: 256,
+            strategy: StrategyConfig::default(),
+            corpus: None,
+        }
+    }
+}
+
+/// An input on which a Cairo function and its reference implementation disagree, minimized by
+/// shrinking. Displays both outputs side by side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DifferentialMismatch {
+    pub function: String,
+    pub args: Vec<CairoValue>,
+    /// The output of the Cairo function, rendered by the comparison.
+    pub cairo: String,
+    /// The output of the reference implementation, rendered by the comparison.
+    pub reference: String,
+}
+
+impl fmt::Display for DifferentialMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} differs from its reference for input [{}]",
+            self.function,
+            self.args.iter().join(", ")
+        )?;
+
+        let width = self
+            .cairo
+            .lines()
+            .map(str::len)
+            .chain(["Cairo".len()])
+            .max()
+            .unwrap_or_default();
+        write!(f, "  {:width$} | Reference", "Cairo")?;
+        for lines in self.cairo.lines().zip_longest(self.reference.lines()) {
+            let (cairo, reference) = match lines {
+                EitherOrBoth::Both(cairo, reference) => (cairo, reference),
+                EitherOrBoth::Left(cairo) => (cairo, ""),
+                EitherOrBoth::Right(reference) => ("", reference),
+            };
+            let marker = if cairo == reference { ' ' } else { '!' };
+            write!(f, "\n{marker} {cairo:width$} | {reference}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DifferentialMismatch {}
+
+/// Renders the output of a Cairo function for a [`DifferentialMismatch`]: the returned value in
+/// the multi-line format, or the panic or error of the call.
+pub fn render_output(output: &Result<CairoValue>) -> String {
+    match output {
+        Ok(value) => format!("{value:#}"),
+        Err(_) => observed_output(output),
+    }
+}
+
+impl CairoProgram {
+    /// Checks that the function named `function_name` returns the same values as `reference`, on
+    /// `config.cases` inputs generated from its parameter types.
+    ///
+    /// Fails with a [`DifferentialMismatch`] for the smallest input found on which the outputs
+    /// differ, or on which the Cairo function panics. With `config.corpus`, the inputs recorded
+    /// for the function are checked first, and the failing input is recorded.
+    pub fn differential_test<R>(
+        &self,
+        function_name: &str,
+        config: &DifferentialConfig,
+        reference: R,
+    ) -> Result<()>
+    where
+        R: Fn(&[CairoValue]) -> CairoValue,
+    {
+        self.differential_test_with(function_name, config, |args, output| {
+            let expected = reference(args);
+            Ok(match output {
+                Ok(value) if *value == expected => None,
+                _ => Some((render_output(output), format!("{expected:#}"))),
+            })
+        })
+    }
+
+    /// Same as [`CairoProgram::differential_test`], with the reference and the comparison done by
+    /// `compare`.
+    ///
+    /// `compare` is given the generated arguments and the output of the Cairo function, and
+    /// returns `None` if the reference agrees, or both outputs rendered for the report otherwise.
+    /// An error of `compare` is reported for the smallest input causing it.
+    pub fn differential_test_with<C>(
+        &self,
+        function_name: &str,
+        config: &DifferentialConfig,
+        compare: C,
+    ) -> Result<()>
+    where
+        C: Fn(&[CairoValue], &Result<CairoValue>) -> Result<Option<(String, String)>>,
+    {
+        let function = self.function(Some(function_name))?;
+        let full_name = function.id.to_string();
+        let strategy = self.args_strategy(function_name, &config.strategy)?;
+        let check = |args: &[CairoValue]| {
+            let output = self.call_values(function_name, args);
+            compare(args, &output)
+        };
+        let mismatch = |args: Vec<CairoValue>, (cairo, reference)| DifferentialMismatch {
+            function: full_name.clone(),
+            args,
+            cairo,
+            reference,
+        };
+
+        let mut corpus = match &config.corpus {
+            Some(path) => FailureCorpus::load(path)?,
+            None => FailureCorpus::default(),
+        };
+        for case in corpus.cases_for(&full_name) {
+            let args = self.decode_args(function, &case.args).with_context(|| {
+                format!("invalid arguments in the failure corpus: {}", case.args)
+            })?;
+            let outputs = check(&args).with_context(|| {
+                format!(
+                    "the comparison failed for input [{}]",
+                    args.iter().join(", ")
+                )
+            })?;
+            if let Some(outputs) = outputs {
+                return Err(mismatch(args, outputs).into());
+            }
+        }
+
+        let mut runner = TestRunner::new(Config {
+            cases: config.cases,
+            failure_persistence: None,
+            ..Config::default()
+        });
+        let args = match runner.run(&strategy, |args| match check(&args) {
+            Ok(None) => Ok(()),
+            Ok(Some(_)) => Err(TestCaseError::fail("outputs differ")),
+            Err(error) => Err(TestCaseError::fail(format!("{error:#}"))),
+        }) {
+            Ok(()) => return Ok(()),
+            Err(TestError::Fail(_, args)) => args,
+            Err(TestError::Abort(reason)) => {
+                bail!("differential test of {function_name} aborted: {reason}")
+            }
+        };
+
+        // Run the minimal input again to report its outputs.
+        let outputs = check(&args).with_context(|| {
+            format!(
+                "the comparison failed for input [{}]",
+                args.iter().join(", ")
+            )
+        })?;
+        let (cairo, reference) = outputs.ok_or_else(|| {
+            anyhow!(
+                "the outputs differed for input [{}], but not when run again",
+                args.iter().join(", ")
+            )
+        })?;
+
+        if let Some(path) = &config.corpus {
+            corpus.record(CorpusCase {
+                function: full_name.clone(),
+                program_hash: self.program_hash(),
+                args: serde_json::Value::Array(args.iter().map(CairoValue::to_json).collect()),
+                result: observed_output(&self.call_values(function_name, &args)),
+            });
+            corpus.save(path)?;
+        }
+        Err(mismatch(args, (cairo, reference)).into())
+    }
+}
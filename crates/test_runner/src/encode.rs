@@ -0,0 +1,235 @@
+use std::fmt;
+
+pub use cairo_lang_runner::Arg;
+use cairo_lang_utils::byte_array::BYTES_IN_WORD;
+use starknet_types_core::felt::Felt;
+
+pub use test_runner_macros::CairoEncode;
+
+use crate::decode::CairoDecode;
+
+/// A Rust type that can be passed as argument to a Cairo function.
+///
+/// Values are encoded with the layout expected by the runner, the inverse of
+/// [`CairoDecode`]: arrays are passed as [`Arg::Array`], everything else as consecutive felts.
+/// Implement it for a Rust mirror of a Cairo struct or enum with `#[derive(CairoEncode)]`.
+pub trait CairoEncode {
+    /// Returns the size of a value of this type, in felts. Arrays count for two felts.
+    fn size() -> usize;
+
+    /// Appends the arguments encoding `self` to `args`.
+    fn encode(&self, args: &mut Vec<Arg>);
+}
+
+/// Encodes `value` into runner arguments. Tuples encode to consecutive arguments, so
+/// `encode_args(&(a, b))` builds the arguments of a function taking `a` and `b`.
+pub fn encode_args<T: CairoEncode>(value: &T) -> Vec<Arg> {
+    let mut args = vec![];
+    value.encode(&mut args);
+    args
+}
+
+/// Appends the selector of the variant at `index`, out of `n_variants`, followed by `padding`
+/// zeros.
+///
+/// Variants smaller than the largest one are padded with leading zeros.
+pub fn encode_variant(args: &mut Vec<Arg>, n_variants: usize, index: usize, padding: usize) {
+    args.push(Arg::Value(Felt::from(variant_selector(n_variants, index))));
+    args.extend((0..padding).map(|_| Arg::Value(Felt::ZERO)));
+}
+
+/// Returns the selector of the variant at `index`, out of `n_variants`.
+///
+/// Inverse of `variant_index` in [`crate::value`].
+fn variant_selector(n_variants: usize, index: usize) -> usize {
+    if n_variants <= 2 {
+        index
+    } else {
+        2 * (n_variants - index) - 1
+    }
+}
+
+/// Encodes `string` as a `ByteArray`: its full 31-byte words, pending word and pending length.
+pub(crate) fn encode_byte_array(string: &str, args: &mut Vec<Arg>) {
+    let chunks = string.as_bytes().chunks(BYTES_IN_WORD).collect::<Vec<_>>();
+    let (pending, words) = match chunks.split_last() {
+        Some((last, words)) if last.len() < BYTES_IN_WORD => (*last, words),
+        _ => (&[][..], chunks.as_slice()),
+    };
+    args.push(Arg::Array(
+        words
+            .iter()
+            .map(|word| Arg::Value(Felt::from_bytes_be_slice(word)))
+            .collect(),
+    ));
+    args.push(Arg::Value(Felt::from_bytes_be_slice(pending)));
+    args.push(Arg::Value(Felt::from(pending.len())));
+}
+
+/// A Cairo `u256`, as its low and high 128-bit limbs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, CairoEncode, CairoDecode)]
+pub struct U256 {
+    pub low: u128,
+    pub high: u128,
+}
+
+impl From<u128> for U256 {
+    fn from(low: u128) -> Self {
+        Self { low, high: 0 }
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = (num_bigint::BigUint::from(self.high) << 128) + self.low;
+        write!(f, "{value}")
+    }
+}
+
+impl CairoEncode for Felt {
+    fn size() -> usize {
+        1
+    }
+
+    fn encode(&self, args: &mut Vec<Arg>) {
+        args.push(Arg::Value(*self));
+    }
+}
+
+macro_rules! impl_cairo_encode_for_integers {
+    ($($t:ty),+) => {
+        $(
+            impl CairoEncode for $t {
+                fn size() -> usize {
+                    1
+                }
+
+                fn encode(&self, args: &mut Vec<Arg>) {
+                    args.push(Arg::Value(Felt::from(*self)));
+                }
+            }
+        )+
+    };
+}
+
+impl_cairo_encode_for_integers!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128);
+
+impl CairoEncode for bool {
+    fn size() -> usize {
+        1
+    }
+
+    fn encode(&self, args: &mut Vec<Arg>) {
+        encode_variant(args, 2, usize::from(*self), 0);
+    }
+}
+
+impl<T: CairoEncode> CairoEncode for Option<T> {
+    fn size() -> usize {
+        1 + T::size()
+    }
+
+    fn encode(&self, args: &mut Vec<Arg>) {
+        match self {
+            Some(value) => {
+                encode_variant(args, 2, 0, 0);
+                value.encode(args);
+            }
+            None => encode_variant(args, 2, 1, T::size()),
+        }
+    }
+}
+
+/// `Array<T>` and `Span<T>`.
+impl<T: CairoEncode> CairoEncode for &[T] {
+    fn size() -> usize {
+        2
+    }
+
+    fn encode(&self, args: &mut Vec<Arg>) {
+        let mut elements = vec![];
+        for value in *self {
+            value.encode(&mut elements);
+        }
+        args.push(Arg::Array(elements));
+    }
+}
+
+/// `Array<T>` and `Span<T>`.
+impl<T: CairoEncode> CairoEncode for Vec<T> {
+    fn size() -> usize {
+        2
+    }
+
+    fn encode(&self, args: &mut Vec<Arg>) {
+        self.as_slice().encode(args);
+    }
+}
+
+/// Fixed-size arrays, laid out inline.
+impl<T: CairoEncode, const N: usize> CairoEncode for [T; N] {
+    fn size() -> usize {
+        N * T::size()
+    }
+
+    fn encode(&self, args: &mut Vec<Arg>) {
+        for value in self {
+            value.encode(args);
+        }
+    }
+}
+
+/// `ByteArray`.
+impl CairoEncode for &str {
+    fn size() -> usize {
+        4
+    }
+
+    fn encode(&self, args: &mut Vec<Arg>) {
+        encode_byte_array(self, args);
+    }
+}
+
+/// `ByteArray`.
+impl CairoEncode for String {
+    fn size() -> usize {
+        4
+    }
+
+    fn encode(&self, args: &mut Vec<Arg>) {
+        encode_byte_array(self, args);
+    }
+}
+
+impl CairoEncode for () {
+    fn size() -> usize {
+        0
+    }
+
+    fn encode(&self, _args: &mut Vec<Arg>) {}
+}
+
+macro_rules! impl_cairo_encode_for_tuples {
+    ($(($($t:ident $i:tt),+)),+) => {
+        $(
+            impl<$($t: CairoEncode),+> CairoEncode for ($($t,)+) {
+                fn size() -> usize {
+                    0 $(+ $t::size())+
+                }
+
+                fn encode(&self, args: &mut Vec<Arg>) {
+                    $(self.$i.encode(args);)+
+                }
+            }
+        )+
+    };
+}
+
+impl_cairo_encode_for_tuples!(
+    (A 0),
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3),
+    (A 0, B 1, C 2, D 3, E 4),
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+);
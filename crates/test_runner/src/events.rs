@@ -0,0 +1,96 @@
+use std::fmt;
+
+use itertools::Itertools;
+use sha3::{Digest, Keccak256};
+use starknet_types_core::felt::Felt;
+
+use crate::value::CairoValue;
+
+/// An event emitted by a run with `emit_event_syscall`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// The address of the contract the event was emitted from.
+    pub from_address: Felt,
+    pub keys: Vec<Felt>,
+    pub data: Vec<Felt>,
+}
+
+impl Event {
+    /// Returns whether the event was emitted through a variant named `name` of a contract's
+    /// `Event` enum, which adds its selector as first key.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.keys.first() == Some(&selector(name))
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "event from {:#x}: keys [{}], data [{}]",
+            self.from_address,
+            self.keys.iter().map(|key| format!("{key:#x}")).join(", "),
+            self.data.iter().join(", ")
+        )
+    }
+}
+
+/// A message to L1 sent by a run with `send_message_to_l1_syscall`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L2ToL1Message {
+    /// The address of the contract the message was sent from.
+    pub from_address: Felt,
+    /// The L1 address the message is sent to.
+    pub to_address: Felt,
+    pub payload: Vec<Felt>,
+}
+
+impl fmt::Display for L2ToL1Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "message from {:#x} to {:#x}: payload [{}]",
+            self.from_address,
+            self.to_address,
+            self.payload.iter().join(", ")
+        )
+    }
+}
+
+/// Returns the Starknet selector of `name`, e.g. of an event or an entry point: the first 250
+/// bits of its Keccak-256 hash, as `selector!` computes it.
+pub fn selector(name: &str) -> Felt {
+    let mut hash: [u8; 32] = Keccak256::digest(name.as_bytes()).into();
+    hash[0] &= 0x03;
+    Felt::from_bytes_be(&hash)
+}
+
+/// An event that a run was expected to emit but didn't. Lists the events of the same type it
+/// emitted instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventNotEmitted {
+    pub type_name: String,
+    pub expected: CairoValue,
+    /// The decoded events of type `type_name` emitted by the run.
+    pub emitted: Vec<CairoValue>,
+}
+
+impl fmt::Display for EventNotEmitted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no {} event {} was emitted",
+            self.type_name, self.expected
+        )?;
+        if self.emitted.is_empty() {
+            return write!(f, ", nor any other {} event", self.type_name);
+        }
+        write!(f, ", {} events emitted:", self.type_name)?;
+        for event in &self.emitted {
+            write!(f, "\n  {event}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for EventNotEmitted {}
@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use cairo_lang_runner::Arg;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// The gas consumed by functions per argument set, as recorded in a snapshot file checked into
+/// the repository.
+///
+/// The file is a JSON object mapping function names to objects mapping argument sets, formatted
+/// by [`args_key`], to the gas consumed. Keys are sorted so that updates produce minimal diffs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GasSnapshot {
+    entries: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl GasSnapshot {
+    /// Loads the snapshot file at `path`, or returns an empty snapshot if it doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read gas snapshot: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse gas snapshot: {}", path.display()))
+    }
+
+    /// Writes the snapshot to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content + "\n")
+            .with_context(|| format!("failed to write gas snapshot: {}", path.display()))
+    }
+
+    /// Records the gas consumed by `function` called with `args`, replacing any previous value.
+    pub fn record(&mut self, function: &str, args: &[Arg], gas: u64) {
+        self.entries
+            .entry(function.to_string())
+            .or_default()
+            .insert(args_key(args), gas);
+    }
+
+    /// Returns the gas consumed by `function` called with the argument set `args_key`.
+    pub fn get(&self, function: &str, args_key: &str) -> Option<u64> {
+        self.entries.get(function)?.get(args_key).copied()
+    }
+
+    /// Returns whether no gas was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds the entries of `other`, replacing the values recorded for the same calls.
+    pub fn merge(&mut self, other: GasSnapshot) {
+        for (function, entries) in other.entries {
+            self.entries.entry(function).or_default().extend(entries);
+        }
+    }
+
+    /// Compares the gas recorded in this snapshot with `baseline`.
+    ///
+    /// Calls missing from this snapshot are ignored, so that a run covering part of the functions
+    /// can be checked against the full snapshot.
+    pub fn compare(&self, baseline: &GasSnapshot, threshold_percent: f64) -> GasReport {
+        let diffs = self
+            .entries
+            .iter()
+            .flat_map(|(function, entries)| {
+                entries.iter().map(move |(args, after)| GasDiff {
+                    function: function.clone(),
+                    args: args.clone(),
+                    before: baseline.get(function, args),
+                    after: *after,
+                })
+            })
+            .filter(|diff| diff.before != Some(diff.after))
+            .collect();
+        GasReport {
+            diffs,
+            threshold_percent,
+        }
+    }
+}
+
+/// Formats an argument set as used in snapshot keys, e.g. `[1, [2, 3]]`.
+pub fn args_key(args: &[Arg]) -> String {
+    format!(
+        "[{}]",
+        args.iter()
+            .map(|arg| match arg {
+                Arg::Value(value) => value.to_string(),
+                Arg::Array(args) => args_key(args),
+            })
+            .join(", ")
+    )
+}
+
+/// A change of the gas consumed by a call, or a call missing from the baseline snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasDiff {
+    pub function: String,
+    pub args: String,
+    /// The gas recorded in the baseline snapshot, `None` for a new call.
+    pub before: Option<u64>,
+    pub after: u64,
+}
+
+impl GasDiff {
+    /// Returns the relative change, in percent of the baseline gas.
+    pub fn change_percent(&self) -> Option<f64> {
+        let before = self.before?;
+        if before == 0 {
+            return None;
+        }
+        Some((self.after as f64 - before as f64) * 100.0 / before as f64)
+    }
+
+    /// Returns whether the gas increased by more than `threshold_percent` of the baseline.
+    pub fn is_regression(&self, threshold_percent: f64) -> bool {
+        match self.before {
+            Some(before) => self.after as f64 > before as f64 * (1.0 + threshold_percent / 100.0),
+            None => false,
+        }
+    }
+}
+
+/// The differences between a run and a gas snapshot, displayed as a table with one row per
+/// changed call.
+#[derive(Debug, Clone)]
+pub struct GasReport {
+    pub diffs: Vec<GasDiff>,
+    /// The tolerated gas increase, in percent of the baseline gas.
+    pub threshold_percent: f64,
+}
+
+impl GasReport {
+    /// Returns the calls whose gas increased beyond the threshold.
+    pub fn regressions(&self) -> impl Iterator<Item = &GasDiff> {
+        self.diffs
+            .iter()
+            .filter(|diff| diff.is_regression(self.threshold_percent))
+    }
+
+    pub fn has_regressions(&self) -> bool {
+        self.regressions().next().is_some()
+    }
+}
+
+impl fmt::Display for GasReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.diffs.is_empty() {
+            return write!(f, "No gas changes.");
+        }
+
+        let rows = self
+            .diffs
+            .iter()
+            .map(|diff| {
+                let change = match (diff.before, diff.change_percent()) {
+                    (Some(before), Some(percent)) => {
+                        format!("{:+} ({percent:+.2}%)", diff.after as i128 - before as i128)
+                    }
+                    (Some(before), None) => format!("{:+}", diff.after as i128 - before as i128),
+                    (None, _) => "new".to_string(),
+                };
+                let status = if diff.is_regression(self.threshold_percent) {
+                    " REGRESSION"
+                } else {
+                    ""
+                };
+                [
+                    diff.function.clone(),
+                    diff.args.clone(),
+                    diff.before
+                        .map(|before| before.to_string())
+                        .unwrap_or_default(),
+                    diff.after.to_string(),
+                    format!("{change}{status}"),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        write_table(
+            f,
+            ["Function", "Arguments", "Before", "After", "Change"],
+            &rows,
+        )
+    }
+}
+
+/// Writes `rows` below `header` as a table, each column padded to its widest cell.
+pub(crate) fn write_table<const N: usize>(
+    f: &mut fmt::Formatter<'_>,
+    header: [&str; N],
+    rows: &[[String; N]],
+) -> fmt::Result {
+    let header = header.map(String::from);
+    let widths = (0..N)
+        .map(|column| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[column].len())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    for (i, row) in [&header].into_iter().chain(rows).enumerate() {
+        if i > 0 {
+            writeln!(f)?;
+        }
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .join("  ");
+        write!(f, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,413 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use cairo_lang_runner::Arg;
+use cairo_lang_sierra::ids::ConcreteTypeId;
+use cairo_lang_sierra::program::ConcreteTypeLongId;
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, ToPrimitive, Zero};
+use serde_json::{Map, Value};
+use starknet_types_core::felt::Felt;
+
+use crate::encode::{encode_byte_array, encode_variant};
+use crate::value::{
+    variant_index, CairoValue, SierraTypes, BOOL, BYTE_ARRAY, SPAN_PREFIX, TUPLE, U256,
+};
+
+impl SierraTypes {
+    /// Encodes the arguments of a function taking `param_types`, given as a JSON array with one
+    /// element per non-builtin parameter.
+    ///
+    /// See [`SierraTypes::encode`] for the JSON representation of each type.
+    pub fn encode_args(&self, param_types: &[ConcreteTypeId], args: &Value) -> Result<Vec<Arg>> {
+        let Value::Array(args) = args else {
+            bail!("Expected a JSON array of arguments, got {}", args);
+        };
+        let param_types = param_types
+            .iter()
+            .filter(|ty| !self.is_builtin(ty))
+            .collect::<Vec<_>>();
+        ensure!(
+            args.len() == param_types.len(),
+            "Expected {} arguments, got {}",
+            param_types.len(),
+            args.len()
+        );
+
+        let mut encoded = vec![];
+        for (i, (ty, arg)) in param_types.into_iter().zip(args).enumerate() {
+            self.encode_into(ty, arg, &mut encoded)
+                .with_context(|| format!("invalid argument {i} of type {}", self.name(ty)))?;
+        }
+        Ok(encoded)
+    }
+
+    /// Encodes a value of type `ty` from its JSON representation:
+    ///
+    /// * integers, felts and other single felt types as JSON numbers or strings, in decimal or
+    ///   `0x`-prefixed hexadecimal.
+    /// * `u256` as a single integer.
+    /// * `bool` as a JSON boolean, and `ByteArray` as a JSON string.
+    /// * arrays, spans and tuples as JSON arrays.
+    /// * structs as JSON arrays of fields, by position. Sierra programs don't record member names,
+    ///   so structs are only accepted as JSON objects keyed by member name when the names are
+    ///   given, e.g. by a contract ABI: see [`SierraTypes::with_member_names`].
+    /// * enums as `{"variant": <index>, "value": <value>}`, `value` being optional for unit
+    ///   variants.
+    pub fn encode(&self, ty: &ConcreteTypeId, value: &Value) -> Result<Vec<Arg>> {
+        let mut encoded = vec![];
+        self.encode_into(ty, value, &mut encoded)?;
+        Ok(encoded)
+    }
+
+    /// Encodes a value of type `ty` from its JSON representation, see [`SierraTypes::encode`],
+    /// into its `Serde` serialization, e.g. as calldata of a contract entry point.
+    pub fn serialize(&self, ty: &ConcreteTypeId, value: &Value) -> Result<Vec<Felt>> {
+        let args = self.encode(ty, value)?;
+        let mut felts = vec![];
+        self.serialize_next(ty, &mut args.as_slice(), &mut felts)?;
+        Ok(felts)
+    }
+
+    fn encode_into(&self, ty: &ConcreteTypeId, value: &Value, args: &mut Vec<Arg>) -> Result<()> {
+        let long_id = self.long_id(ty)?;
+        match long_id.generic_id.0.as_str() {
+            "felt252" | "ContractAddress" | "ClassHash" | "StorageAddress"
+            | "StorageBaseAddress" => {
+                let value = self.parse_integer(ty, value)?;
+                let prime = BigInt::from(Felt::MAX.to_biguint()) + BigInt::one();
+                ensure!(
+                    -&prime < value && value < prime,
+                    "{} is out of range for type {}",
+                    value,
+                    self.name(ty)
+                );
+                args.push(Arg::Value(Felt::from(value)));
+            }
+            generic_id @ ("bytes31" | "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16"
+            | "i32" | "i64" | "i128") => {
+                let value = self.parse_integer(ty, value)?;
+                let (min, max) = integer_bounds(generic_id);
+                ensure!(
+                    min <= value && value <= max,
+                    "{} is out of range for type {}",
+                    value,
+                    self.name(ty)
+                );
+                args.push(Arg::Value(Felt::from(value)));
+            }
+            "Snapshot" => self.encode_into(self.inner_type(long_id)?, value, args)?,
+            "NonZero" => {
+                let start = args.len();
+                self.encode_into(self.inner_type(long_id)?, value, args)?;
+                ensure!(
+                    args[start..]
+                        .iter()
+                        .any(|arg| !matches!(arg, Arg::Value(felt) if *felt == Felt::ZERO)),
+                    "Expected a non-zero value for type {}",
+                    self.name(ty)
+                );
+            }
+            "Array" => {
+                let Value::Array(elements) = value else {
+                    bail!(self.mismatch("a JSON array", ty, value));
+                };
+                let element = self.inner_type(long_id)?;
+                let mut encoded = vec![];
+                for (i, value) in elements.iter().enumerate() {
+                    self.encode_into(element, value, &mut encoded)
+                        .with_context(|| format!("invalid element {i} of {}", self.name(ty)))?;
+                }
+                args.push(Arg::Array(encoded));
+            }
+            "Struct" => self.encode_struct(ty, long_id, value, args)?,
+            "Enum" => self.encode_enum(ty, long_id, value, args)?,
+            _ => bail!("Unsupported argument type: {}", self.name(ty)),
+        }
+        Ok(())
+    }
+
+    fn encode_struct(
+        &self,
+        ty: &ConcreteTypeId,
+        long_id: &ConcreteTypeLongId,
+        value: &Value,
+        args: &mut Vec<Arg>,
+    ) -> Result<()> {
+        let name = self.user_type_name(ty)?;
+        let members = self.type_args(long_id).collect::<Vec<_>>();
+
+        match (name.as_str(), value) {
+            (U256, Value::Number(_) | Value::String(_)) => {
+                let value = self.parse_integer(ty, value)?;
+                ensure!(
+                    value.sign() != Sign::Minus && value.bits() <= 256,
+                    "{} is out of range for type {}",
+                    value,
+                    self.name(ty)
+                );
+                let mask = (BigInt::one() << 128) - 1;
+                args.push(Arg::Value(Felt::from(&value & &mask)));
+                args.push(Arg::Value(Felt::from(value >> 128)));
+                return Ok(());
+            }
+            (BYTE_ARRAY, Value::String(string)) => {
+                encode_byte_array(string, args);
+                return Ok(());
+            }
+            (name, Value::Array(_)) if name.starts_with(SPAN_PREFIX) => {
+                let [array] = members.as_slice() else {
+                    bail!("Unexpected span type: {}", self.name(ty));
+                };
+                return self.encode_into(array, value, args);
+            }
+            _ => {}
+        }
+
+        let member_names = self.member_names(ty, members.len());
+        let fields = match value {
+            Value::Array(fields) => fields.iter().collect::<Vec<_>>(),
+            Value::Object(fields) if name != TUPLE => {
+                let Some(names) = member_names else {
+                    bail!(
+                        "{}: Sierra programs don't record member names",
+                        self.mismatch("a JSON array of fields", ty, value)
+                    );
+                };
+                struct_fields(fields, names)
+                    .with_context(|| format!("invalid fields for {}", self.name(ty)))?
+            }
+            _ if name == TUPLE => bail!(self.mismatch("a JSON array", ty, value)),
+            _ if member_names.is_some() => {
+                bail!(self.mismatch("a JSON object or array of fields", ty, value))
+            }
+            _ => bail!(self.mismatch("a JSON array of fields", ty, value)),
+        };
+        ensure!(
+            fields.len() == members.len(),
+            "Expected {} fields for type {}, got {}",
+            members.len(),
+            self.name(ty),
+            fields.len()
+        );
+        for (i, (member, field)) in members.into_iter().zip(fields).enumerate() {
+            self.encode_into(member, field, args)
+                .with_context(|| match member_names {
+                    Some(names) => format!("invalid member `{}` of {}", names[i], self.name(ty)),
+                    None => format!("invalid field {i} of {}", self.name(ty)),
+                })?;
+        }
+        Ok(())
+    }
+
+    fn encode_enum(
+        &self,
+        ty: &ConcreteTypeId,
+        long_id: &ConcreteTypeLongId,
+        value: &Value,
+        args: &mut Vec<Arg>,
+    ) -> Result<()> {
+        let variants = self.type_args(long_id).collect::<Vec<_>>();
+        let (variant, variant_value) = match (self.user_type_name(ty)?.as_str(), value) {
+            (BOOL, Value::Bool(value)) => (usize::from(*value), &Value::Null),
+            (_, Value::Object(object)) => {
+                let variant = object
+                    .get("variant")
+                    .and_then(Value::as_u64)
+                    .and_then(|variant| usize::try_from(variant).ok())
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Expected an integer `variant` for type {}, got {}",
+                            self.name(ty),
+                            value
+                        )
+                    })?;
+                if let Some(key) = object.keys().find(|key| *key != "variant" && *key != "value")
+                {
+                    bail!("Unexpected key `{}` for type {}", key, self.name(ty));
+                }
+                (variant, object.get("value").unwrap_or(&Value::Null))
+            }
+            _ => bail!(self.mismatch(r#"a {"variant": ..., "value": ...} object"#, ty, value)),
+        };
+        let variant_type = variants.get(variant).ok_or_else(|| {
+            anyhow!(
+                "Variant {} out of range for type {}, which has {} variants",
+                variant,
+                self.name(ty),
+                variants.len()
+            )
+        })?;
+
+        // Unit variants may omit their value.
+        let variant_value = match variant_value {
+            Value::Null if self.size(variant_type)? == 0 => &Value::Array(vec![]),
+            value => value,
+        };
+
+        let padding = self.size(ty)? - 1 - self.size(variant_type)?;
+        encode_variant(args, variants.len(), variant, padding);
+        self.encode_into(variant_type, variant_value, args)
+            .with_context(|| format!("invalid value of variant {variant} of {}", self.name(ty)))
+    }
+
+    /// Converts runner arguments laid out like a value of type `ty`, from the start of `args`, to
+    /// its `Serde` serialization, advancing past them. Inverse of `deserialize_next`.
+    fn serialize_next(
+        &self,
+        ty: &ConcreteTypeId,
+        args: &mut &[Arg],
+        felts: &mut Vec<Felt>,
+    ) -> Result<()> {
+        let long_id = self.long_id(ty)?;
+        match long_id.generic_id.0.as_str() {
+            "Snapshot" | "NonZero" => self.serialize_next(self.inner_type(long_id)?, args, felts)?,
+            "Struct" => {
+                for member in self.type_args(long_id) {
+                    self.serialize_next(member, args, felts)?;
+                }
+            }
+            "Array" => {
+                let Some((Arg::Array(elements), rest)) = args.split_first() else {
+                    bail!("Expected an array for type {}", self.name(ty));
+                };
+                *args = rest;
+                let element = self.inner_type(long_id)?;
+                let mut cursor = elements.as_slice();
+                let mut serialized = vec![];
+                let mut len = 0;
+                while !cursor.is_empty() {
+                    self.serialize_next(element, &mut cursor, &mut serialized)?;
+                    len += 1;
+                }
+                felts.push(Felt::from(len));
+                felts.extend(serialized);
+            }
+            "Enum" => {
+                let variants = self.type_args(long_id).collect::<Vec<_>>();
+                let variant = take_value(args)?
+                    .to_usize()
+                    .and_then(|selector| variant_index(variants.len(), selector))
+                    .ok_or_else(|| anyhow!("Invalid variant selector for {}", self.name(ty)))?;
+                // Skip the padding of variants smaller than the largest one.
+                let padding = self.size(ty)? - 1 - self.size(variants[variant])?;
+                for _ in 0..padding {
+                    take_value(args)?;
+                }
+                felts.push(Felt::from(variant));
+                self.serialize_next(variants[variant], args, felts)?;
+            }
+            _ if self.size(ty)? == 1 => felts.push(take_value(args)?),
+            _ => bail!("{} can't be serialized", self.name(ty)),
+        }
+        Ok(())
+    }
+
+    fn parse_integer(&self, ty: &ConcreteTypeId, value: &Value) -> Result<BigInt> {
+        let parsed = match value {
+            Value::Number(number) => parse_integer_str(&number.to_string()),
+            Value::String(string) => parse_integer_str(string),
+            _ => None,
+        };
+        parsed.ok_or_else(|| anyhow!(self.mismatch("an integer", ty, value)))
+    }
+
+    fn mismatch(&self, expected: &str, ty: &ConcreteTypeId, value: &Value) -> String {
+        format!(
+            "Expected {} for type {}, got {}",
+            expected,
+            self.name(ty),
+            value
+        )
+    }
+}
+
+impl CairoValue {
+    /// Returns the JSON representation of this value accepted by [`SierraTypes::encode`].
+    ///
+    /// Integers are represented as decimal strings, as JSON numbers can't represent all of them
+    /// exactly, and structs as arrays of fields.
+    pub fn to_json(&self) -> Value {
+        match self {
+            CairoValue::Felt(value) => Value::String(value.to_string()),
+            CairoValue::Uint(value) => Value::String(value.to_string()),
+            CairoValue::Int(value) => Value::String(value.to_string()),
+            CairoValue::Bool(value) => Value::Bool(*value),
+            CairoValue::String(value) => Value::String(value.clone()),
+            CairoValue::Array(values)
+            | CairoValue::Tuple(values)
+            | CairoValue::Struct { fields: values, .. } => {
+                Value::Array(values.iter().map(CairoValue::to_json).collect())
+            }
+            CairoValue::Enum { variant, value, .. } => serde_json::json!({
+                "variant": variant,
+                "value": value.to_json(),
+            }),
+            CairoValue::Raw { felts, .. } => Value::Array(
+                felts
+                    .iter()
+                    .map(|felt| Value::String(felt.to_string()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Returns the fields of a struct given as a JSON object keyed by the member names `names`.
+fn struct_fields<'a>(object: &'a Map<String, Value>, names: &[String]) -> Result<Vec<&'a Value>> {
+    if let Some(key) = object.keys().find(|key| !names.contains(key)) {
+        bail!("Unexpected member `{}`", key);
+    }
+    names
+        .iter()
+        .map(|name| {
+            object
+                .get(name)
+                .ok_or_else(|| anyhow!("Missing member `{name}`"))
+        })
+        .collect()
+}
+
+/// Takes the single felt argument at the start of `args`.
+fn take_value(args: &mut &[Arg]) -> Result<Felt> {
+    let Some((Arg::Value(value), rest)) = args.split_first() else {
+        bail!("Expected a single felt argument");
+    };
+    *args = rest;
+    Ok(*value)
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal integer, optionally negative.
+fn parse_integer_str(string: &str) -> Option<BigInt> {
+    let (negative, digits) = match string.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, string),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => BigInt::parse_bytes(hex.as_bytes(), 16)?,
+        None => BigInt::parse_bytes(digits.as_bytes(), 10)?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Returns the inclusive bounds of the integer type with the given generic id.
+fn integer_bounds(generic_id: &str) -> (BigInt, BigInt) {
+    let (signed, bits): (bool, usize) = match generic_id {
+        "bytes31" => (false, 248),
+        "u8" => (false, 8),
+        "u16" => (false, 16),
+        "u32" => (false, 32),
+        "u64" => (false, 64),
+        "u128" => (false, 128),
+        "i8" => (true, 8),
+        "i16" => (true, 16),
+        "i32" => (true, 32),
+        "i64" => (true, 64),
+        "i128" => (true, 128),
+        _ => unreachable!("not an integer type: {generic_id}"),
+    };
+    if signed {
+        let bound: BigInt = BigInt::one() << (bits - 1);
+        (-&bound, bound - 1)
+    } else {
+        (BigInt::zero(), (BigInt::one() << bits) - 1)
+    }
+}
@@ -1,3 +1,24 @@
+// Lets `#[derive(CairoDecode)]` refer to `::test_runner` from within this crate.
+extern crate self as test_runner;
+
+pub mod cheatcodes;
+pub mod context;
+pub mod contract;
+pub mod corpus;
+pub mod coverage;
+pub mod decode;
 pub mod deserialization;
+pub mod differential;
+pub mod encode;
+pub mod events;
+pub mod gas;
+pub mod json_args;
 pub mod manual_types;
+pub mod panic;
+pub mod profile;
+pub mod program;
+pub mod resources;
+pub mod snapshot;
+pub mod strategy;
 pub mod test_utils;
+pub mod value;
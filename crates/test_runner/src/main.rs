@@ -1,9 +1,8 @@
 use anyhow::Result;
-use cairo_vm::Felt252 as Felt;
 use clap::Parser;
 use log::info;
 use std::path::{Path, PathBuf};
-use test_runner::test_utils::load_and_run_cairo_function;
+use test_runner::program::CairoProgram;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -35,7 +34,7 @@ fn main() -> Result<()> {
     info!("Running function {} with args {}", args.function, args.args);
 
     let sierra_path = Path::new(&args.sierra_path);
-    let result = load_and_run_cairo_function::<Vec<Felt>>(&args.function, &sierra_path, &args.args)?;
+    let result = CairoProgram::load(sierra_path)?.call(&args.function, &args.args)?;
     println!("Result: {:?}", result);
 
     Ok(())
@@ -1,25 +1,17 @@
+use crate::decode::{CairoDecode, DecodeError, FeltReader};
+use crate::encode::CairoEncode;
 use cairo_lang_runner::short_string::{as_cairo_short_string, as_cairo_short_string_ex};
 use cairo_lang_utils::byte_array::{BYTES_IN_WORD, BYTE_ARRAY_MAGIC};
 use itertools::Itertools;
 use num_traits::cast::ToPrimitive;
 use starknet_types_core::felt::Felt;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, CairoDecode, CairoEncode)]
 pub struct MyStruct {
     pub field_0: u128,
     pub field_1: u32,
 }
 
-impl TryFrom<Vec<Felt>> for MyStruct {
-    type Error = String;
-    fn try_from(vec: Vec<Felt>) -> Result<Self, String> {
-        Ok(Self {
-            field_0: vec[0].try_into().unwrap(),
-            field_1: vec[1].try_into().unwrap(),
-        })
-    }
-}
-
 #[derive(Debug, PartialEq, Eq)]
 pub struct Stack(Vec<u128>);
 
@@ -29,13 +21,24 @@ impl Stack {
     }
 }
 
-impl TryFrom<Vec<Felt>> for Stack {
-    type Error = String;
+/// Decoded from the `ByteArray` debug representation of the stack, e.g. `[1, 2, 3]`.
+impl CairoDecode for Stack {
+    fn size() -> usize {
+        <String as CairoDecode>::size()
+    }
 
-    fn try_from(values: Vec<Felt>) -> Result<Self, Self::Error> {
-        Ok(Stack(
-            from_felt_byte_array(values).map_err(|e| e.to_string())?,
-        ))
+    fn decode(reader: &mut FeltReader<'_>) -> Result<Self, DecodeError> {
+        let string: String = reader.read()?;
+        let values_str = string.trim_matches(|c| c == '[' || c == ']');
+        if values_str.is_empty() {
+            return Ok(Stack(vec![]));
+        }
+        values_str
+            .split(',')
+            .map(|s| s.trim().parse::<u128>())
+            .collect::<Result<Vec<_>, _>>()
+            .map(Stack)
+            .map_err(|e| DecodeError::InvalidValue(format!("stack {string:?}: {e}")))
     }
 }
 
@@ -56,33 +59,22 @@ pub fn from_felt_byte_array(felt_bytes: Vec<Felt>) -> Result<Vec<u128>, ParseInt
     values
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, CairoDecode)]
 pub struct U8(pub u8);
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, CairoDecode)]
 pub struct U16(pub u16);
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, CairoDecode)]
 pub struct U32(pub u32);
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, CairoDecode)]
 pub struct U64(pub u64);
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, CairoDecode)]
 pub struct U128(pub u128);
 
 use std::{num::ParseIntError, ops::Deref, vec::IntoIter};
 
-macro_rules! impl_try_from_felt_vec_and_deref {
+macro_rules! impl_deref {
     ($($t:ty, $inner:ty),+) => {
         $(
-            impl TryFrom<Vec<Felt>> for $t {
-                type Error = String;
-
-                fn try_from(values: Vec<Felt>) -> Result<Self, Self::Error> {
-                    match values.len() {
-                        1 => Ok(Self(values[0].try_into().map_err(|e| format!("Conversion error: {}", e))?)),
-                        _ => Err("Invalid number of values".to_string()),
-                    }
-                }
-            }
-
             impl Deref for $t {
                 type Target = $inner;
 
@@ -94,7 +86,7 @@ macro_rules! impl_try_from_felt_vec_and_deref {
     };
 }
 
-impl_try_from_felt_vec_and_deref!(U8, u8, U16, u16, U32, u32, U64, u64, U128, u128);
+impl_deref!(U8, u8, U16, u16, U32, u32, U64, u64, U128, u128);
 
 /// Formats the given felts as a debug string.
 fn format_for_debug(mut felts: IntoIter<Felt>) -> String {
@@ -0,0 +1,85 @@
+use std::fmt;
+
+use cairo_lang_runner::short_string::as_cairo_short_string;
+use cairo_lang_utils::byte_array::BYTE_ARRAY_MAGIC;
+use itertools::Itertools;
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt;
+
+use crate::value::byte_array_string;
+
+/// The panic of a Cairo function, with its decoded panic data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CairoPanic {
+    /// The raw panic data.
+    pub felts: Vec<Felt>,
+    /// Each panic data felt decoded as a short string, if it is one.
+    pub short_strings: Vec<Option<String>>,
+    /// The message of panics raised with a `ByteArray`, e.g. by `panic!` and `assert!` with a
+    /// formatted message.
+    pub message: Option<String>,
+    /// What the function printed before panicking, if known.
+    pub output: String,
+}
+
+impl CairoPanic {
+    pub fn new(felts: Vec<Felt>) -> Self {
+        Self {
+            short_strings: felts.iter().map(as_cairo_short_string).collect(),
+            message: byte_array_message(&felts),
+            felts,
+            output: String::new(),
+        }
+    }
+
+    /// Sets what the function printed before panicking.
+    pub fn with_output(mut self, output: String) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Returns the panic message: the `ByteArray` message if any, otherwise the panic data, as
+    /// short strings where possible.
+    pub fn reason(&self) -> String {
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+        self.felts
+            .iter()
+            .zip(&self.short_strings)
+            .map(|(felt, short_string)| match short_string {
+                Some(short_string) => short_string.clone(),
+                None => felt.to_string(),
+            })
+            .join(", ")
+    }
+}
+
+impl fmt::Display for CairoPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Function panicked: {}", self.reason())?;
+        if !self.output.is_empty() {
+            write!(f, "\n\nCairo output:\n{}", self.output.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CairoPanic {}
+
+/// Decodes panic data serialized as `[BYTE_ARRAY_MAGIC, n_full_words, ..full_words,
+/// pending_word, pending_word_len]`.
+fn byte_array_message(felts: &[Felt]) -> Option<String> {
+    let (magic, rest) = felts.split_first()?;
+    if *magic != Felt::from_hex(BYTE_ARRAY_MAGIC).ok()? {
+        return None;
+    }
+    let (n_full_words, rest) = rest.split_first()?;
+    let n_full_words = n_full_words.to_usize()?;
+    let full_words = rest.get(..n_full_words)?;
+    let [pending_word, pending_word_len, ..] = rest.get(n_full_words..)? else {
+        return None;
+    };
+
+    byte_array_string(full_words, pending_word, pending_word_len.to_usize()?)
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use cairo_lang_runner::profiling::ProfilingInfo;
+use cairo_lang_sierra::program::ProgramArtifact;
+use itertools::Itertools;
+
+/// The number of VM steps executed per call stack of user functions, accumulated across runs.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// Steps by call stack, each function being its index in the Sierra program.
+    stack_steps: HashMap<Vec<usize>, usize>,
+}
+
+impl Profile {
+    /// Adds the call stacks of a run, from its profiling info.
+    pub fn record(&mut self, profiling_info: &ProfilingInfo) {
+        for (stack, steps) in profiling_info.stack_trace_weights.iter_sorted() {
+            *self.stack_steps.entry(stack.clone()).or_default() += steps;
+        }
+    }
+
+    /// Resolves the functions of the recorded call stacks to their names in `artifact`.
+    pub fn report(&self, artifact: &ProgramArtifact) -> ProfileReport {
+        let funcs = &artifact.program.funcs;
+        let mut stacks = BTreeMap::new();
+        for (stack, steps) in &self.stack_steps {
+            let stack = stack
+                .iter()
+                .map(|idx| match funcs.get(*idx) {
+                    Some(function) => function.id.to_string(),
+                    None => format!("[{idx}]"),
+                })
+                .collect::<Vec<_>>();
+            *stacks.entry(stack).or_default() += steps;
+        }
+        ProfileReport { stacks }
+    }
+}
+
+/// The VM steps executed per call stack, from the outermost function to the function executing
+/// them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileReport {
+    stacks: BTreeMap<Vec<String>, usize>,
+}
+
+impl ProfileReport {
+    /// Adds the call stacks of `other`.
+    pub fn merge(&mut self, other: ProfileReport) {
+        for (stack, steps) in other.stacks {
+            *self.stacks.entry(stack).or_default() += steps;
+        }
+    }
+
+    /// Returns the steps of every function, sorted from the most to the least expensive in total.
+    pub fn functions(&self) -> Vec<FunctionSteps> {
+        let mut functions: HashMap<&str, FunctionSteps> = HashMap::new();
+        for (stack, steps) in &self.stacks {
+            // Recursive functions appear several times in a stack, but execute its steps once.
+            for name in stack.iter().unique() {
+                functions
+                    .entry(name)
+                    .or_insert_with(|| FunctionSteps::new(name))
+                    .total_steps += steps;
+            }
+            if let Some(name) = stack.last() {
+                functions
+                    .entry(name)
+                    .or_insert_with(|| FunctionSteps::new(name))
+                    .self_steps += steps;
+            }
+        }
+        functions
+            .into_values()
+            .sorted_by(|a, b| {
+                (b.total_steps, b.self_steps, &a.name).cmp(&(a.total_steps, a.self_steps, &b.name))
+            })
+            .collect()
+    }
+
+    /// Returns a table of the `n` most expensive functions.
+    pub fn top(&self, n: usize) -> TopFunctions {
+        let mut functions = self.functions();
+        functions.truncate(n);
+        TopFunctions(functions)
+    }
+
+    /// Formats the report in the folded stacks format read by flamegraph tools, one
+    /// `outer;inner steps` line per call stack.
+    pub fn to_folded(&self) -> String {
+        let mut folded = String::new();
+        for (stack, steps) in &self.stacks {
+            folded.push_str(&format!("{} {steps}\n", stack.join(";")));
+        }
+        folded
+    }
+
+    /// Writes the report to `path` in the folded stacks format.
+    pub fn write_folded(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_folded())
+            .with_context(|| format!("failed to write profile: {}", path.display()))
+    }
+}
+
+/// The VM steps executed by a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionSteps {
+    pub name: String,
+    /// The steps executed by the function itself, excluding the functions it called.
+    pub self_steps: usize,
+    /// The steps executed by the function and the functions it called.
+    pub total_steps: usize,
+}
+
+impl FunctionSteps {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            self_steps: 0,
+            total_steps: 0,
+        }
+    }
+}
+
+/// The most expensive functions of a profile, displayed as a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopFunctions(pub Vec<FunctionSteps>);
+
+impl fmt::Display for TopFunctions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .0
+            .iter()
+            .map(|function| function.name.len())
+            .chain(["Function".len()])
+            .max()
+            .unwrap_or_default();
+        write!(
+            f,
+            "{:width$}  {:>12}  {:>12}",
+            "Function", "Total steps", "Self steps"
+        )?;
+        for function in &self.0 {
+            write!(
+                f,
+                "\n{:width$}  {:>12}  {:>12}",
+                function.name, function.total_steps, function.self_steps
+            )?;
+        }
+        Ok(())
+    }
+}
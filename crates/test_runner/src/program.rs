@@ -0,0 +1,624 @@
+use crate::cheatcodes::run_with_cheatcodes;
+use crate::context::ExecutionContext;
+use crate::coverage::{Coverage, CoverageReport};
+use crate::decode::{decode_felts, CairoDecode};
+use crate::encode::{encode_args, CairoEncode};
+use crate::events::{Event, EventNotEmitted, L2ToL1Message};
+use crate::panic::CairoPanic;
+use crate::profile::{Profile, ProfileReport};
+use crate::resources::ExecutionResources;
+use crate::snapshot::{assert_snapshot, Snapshot, SnapshotUpdate};
+use crate::strategy::StrategyConfig;
+use crate::value::{CairoValue, SierraTypes};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use cairo_lang_runner::{
+    Arg, ProfilingInfoCollectionConfig, RunResultStarknet, RunResultValue, SierraCasmRunner,
+};
+use cairo_lang_sierra::ids::FunctionId;
+use cairo_lang_sierra::program::{Function, ProgramArtifact, VersionedProgram};
+use log::debug;
+use proptest::strategy::BoxedStrategy;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use starknet_types_core::felt::Felt;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+const DEFAULT_MAIN_FUNCTION: &str = "::main";
+const EXECUTABLE_NAME: &str = "main";
+
+/// A Sierra program compiled to CASM once, and callable any number of times.
+///
+/// Building a `SierraCasmRunner` is by far the most expensive part of running a Cairo function, so
+/// callers invoking the same program repeatedly (e.g. property tests) should load it once and
+/// reuse it through [`CairoProgram::call`] and [`CairoProgram::call_typed`].
+pub struct CairoProgram {
+    runner: SierraCasmRunner,
+    artifact: ProgramArtifact,
+    types: SierraTypes,
+    available_gas: Option<usize>,
+    /// The Starknet context of every run.
+    context: ExecutionContext,
+    /// Whether what the runs print is also written to stdout as they print it.
+    stream_output: bool,
+    /// The statements executed by the runs so far, if coverage is enabled.
+    coverage: Option<Mutex<Coverage>>,
+    /// The steps per call stack of the runs so far, if profiling is enabled.
+    profile: Option<Mutex<Profile>>,
+}
+
+/// The result of running a Cairo function with a [`CairoProgram`].
+pub struct CairoRun {
+    /// The raw run result, with the final Starknet state.
+    pub result: RunResultStarknet,
+    /// What the function printed with `print!`, `println!` or `core::debug::print`, in the format
+    /// of `scarb cairo-run`.
+    pub output: String,
+    /// The events emitted by the function, in order.
+    pub events: Vec<Event>,
+    /// The messages to L1 sent by the function, in order.
+    pub l2_to_l1_messages: Vec<L2ToL1Message>,
+}
+
+/// What a [`CairoProgram`] collects from its runs in addition to their results. Collecting
+/// anything makes runs slower, as their trace is analyzed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Instrumentation {
+    /// Collect the executed statements, for [`CairoProgram::coverage_report`].
+    pub coverage: bool,
+    /// Collect the steps per call stack, for [`CairoProgram::profile_report`].
+    pub profile: bool,
+}
+
+impl CairoProgram {
+    /// Loads the Sierra program JSON file at `sierra_path` and compiles it, with unlimited gas.
+    pub fn load(sierra_path: &Path) -> Result<Self> {
+        Self::new(load_artifact(sierra_path)?, Some(usize::MAX))
+    }
+
+    /// Compiles the given Sierra program to CASM.
+    ///
+    /// # Arguments
+    ///
+    /// * `artifact` - The Sierra program and its debug info.
+    /// * `available_gas` - The gas available to every call, or `None` to disable gas metering.
+    pub fn new(artifact: ProgramArtifact, available_gas: Option<usize>) -> Result<Self> {
+        Self::instrumented(artifact, available_gas, Instrumentation::default())
+    }
+
+    /// Same as [`CairoProgram::new`], also collecting the statements executed by every run for
+    /// [`CairoProgram::coverage_report`].
+    pub fn with_coverage(artifact: ProgramArtifact, available_gas: Option<usize>) -> Result<Self> {
+        let instrumentation = Instrumentation {
+            coverage: true,
+            ..Default::default()
+        };
+        Self::instrumented(artifact, available_gas, instrumentation)
+    }
+
+    /// Same as [`CairoProgram::new`], also collecting what `instrumentation` asks for from every
+    /// run.
+    pub fn instrumented(
+        artifact: ProgramArtifact,
+        available_gas: Option<usize>,
+        instrumentation: Instrumentation,
+    ) -> Result<Self> {
+        let profiling = instrumentation.coverage || instrumentation.profile;
+        let runner = SierraCasmRunner::new(
+            artifact.program.clone(),
+            available_gas.map(|_| Default::default()),
+            Default::default(),
+            profiling.then(ProfilingInfoCollectionConfig::default),
+        )?;
+
+        Ok(Self {
+            runner,
+            types: SierraTypes::new(&artifact),
+            artifact,
+            available_gas,
+            context: ExecutionContext::default(),
+            stream_output: false,
+            coverage: instrumentation.coverage.then(Default::default),
+            profile: instrumentation.profile.then(Default::default),
+        })
+    }
+
+    /// Sets the Starknet context every call runs in, e.g. the block number and caller address
+    /// read by the functions.
+    pub fn with_context(mut self, context: ExecutionContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Sets whether what the calls print is also written to stdout as they print it, e.g. to
+    /// follow a long run. It is returned in [`CairoRun::output`] either way.
+    pub fn with_streamed_output(mut self, stream_output: bool) -> Self {
+        self.stream_output = stream_output;
+        self
+    }
+
+    /// Names the members of the structs of `member_names`, see
+    /// [`SierraTypes::with_member_names`].
+    pub fn with_member_names(mut self, member_names: HashMap<String, Vec<String>>) -> Self {
+        self.types = self.types.with_member_names(member_names);
+        self
+    }
+
+    /// Returns the Starknet context every call runs in.
+    pub fn context(&self) -> &ExecutionContext {
+        &self.context
+    }
+
+    /// Returns the underlying runner.
+    pub fn runner(&self) -> &SierraCasmRunner {
+        &self.runner
+    }
+
+    /// Returns the Sierra program and its debug info.
+    pub fn artifact(&self) -> &ProgramArtifact {
+        &self.artifact
+    }
+
+    /// Returns the type declarations of the program.
+    pub fn types(&self) -> &SierraTypes {
+        &self.types
+    }
+
+    /// Finds the function to run, by name or among the program executables.
+    pub fn function(&self, name: Option<&str>) -> Result<&Function> {
+        main_function(&self.runner, &self.artifact, name)
+    }
+
+    /// Returns the fully qualified name and number of non-builtin parameters of every named
+    /// function of the program.
+    pub fn functions(&self) -> Vec<(&str, usize)> {
+        self.artifact
+            .program
+            .funcs
+            .iter()
+            .filter_map(|function| {
+                let name = function.id.debug_name.as_deref()?;
+                let n_params = function
+                    .signature
+                    .param_types
+                    .iter()
+                    .filter(|ty| !self.types.is_builtin(ty))
+                    .count();
+                Some((name, n_params))
+            })
+            .collect()
+    }
+
+    /// Runs `function` with the given arguments, returning the raw run result and what the
+    /// function printed.
+    pub fn run(&self, function: &Function, args: &[Arg]) -> Result<CairoRun> {
+        self.run_in_context(function, args, &self.context)
+    }
+
+    /// Same as [`CairoProgram::run`], in `context` instead of the context of the program. The
+    /// final Starknet state is returned in the run result.
+    ///
+    /// The function can use the cheatcodes of
+    /// [`crate::cheatcodes::CheatcodeHintProcessor`].
+    pub fn run_in_context(
+        &self,
+        function: &Function,
+        args: &[Arg],
+        context: &ExecutionContext,
+    ) -> Result<CairoRun> {
+        let run = run_with_cheatcodes(
+            &self.runner,
+            function,
+            args,
+            self.available_gas,
+            context,
+            self.stream_output,
+        )?;
+        if let Some(profiling_info) = &run.result.profiling_info {
+            if let Some(coverage) = &self.coverage {
+                coverage
+                    .lock()
+                    .expect("coverage lock poisoned")
+                    .record(profiling_info);
+            }
+            if let Some(profile) = &self.profile {
+                profile
+                    .lock()
+                    .expect("profile lock poisoned")
+                    .record(profiling_info);
+            }
+        }
+        Ok(run)
+    }
+
+    /// Returns the VM steps executed per call stack of user functions by the runs so far. Fails if
+    /// the program wasn't built with [`Instrumentation::profile`].
+    pub fn profile_report(&self) -> Result<ProfileReport> {
+        Ok(self
+            .profile
+            .as_ref()
+            .ok_or_else(|| anyhow!("profiling is not enabled for this program"))?
+            .lock()
+            .expect("profile lock poisoned")
+            .report(&self.artifact))
+    }
+
+    /// Returns the Cairo source lines executed by the runs so far. Fails if the program wasn't
+    /// built [`CairoProgram::with_coverage`], or lacks the statements code locations debug info.
+    pub fn coverage_report(&self) -> Result<CoverageReport> {
+        self.coverage
+            .as_ref()
+            .ok_or_else(|| anyhow!("coverage is not enabled for this program"))?
+            .lock()
+            .expect("coverage lock poisoned")
+            .report(&self.artifact)
+    }
+
+    /// Returns the gas consumed by a run, or `None` if gas metering is disabled.
+    pub fn gas_consumed(&self, result: &RunResultStarknet) -> Option<u64> {
+        let available = self.available_gas? as u128;
+        // Functions without a `GasBuiltin` parameter don't consume gas.
+        let Some(remaining) = result.gas_counter else {
+            return Some(0);
+        };
+        let remaining = u128::try_from(remaining).ok()?;
+        u64::try_from(available.checked_sub(remaining)?).ok()
+    }
+
+    /// Runs `function` once per argument set, in parallel, returning the raw run results in the
+    /// order of `args`.
+    pub fn run_batch(&self, function: &Function, args: &[Vec<Arg>]) -> Vec<Result<CairoRun>> {
+        args.par_iter()
+            .map(|args| self.run(function, args))
+            .collect()
+    }
+
+    /// Encodes the JSON array `args` according to the parameter types of `function`.
+    ///
+    /// See [`SierraTypes::encode`] for the JSON representation of each type.
+    pub fn encode_args(&self, function: &Function, args: &str) -> Result<Vec<Arg>> {
+        let args: serde_json::Value =
+            serde_json::from_str(args).context("failed to parse arguments")?;
+        self.types.encode_args(&function.signature.param_types, &args)
+    }
+
+    /// Returns the arguments of `function` given as a JSON array, e.g. stored in a failure corpus,
+    /// as values to be passed to [`CairoProgram::call_values`].
+    pub fn decode_args(
+        &self,
+        function: &Function,
+        args: &serde_json::Value,
+    ) -> Result<Vec<CairoValue>> {
+        let param_types = &function.signature.param_types;
+        let encoded = self.types.encode_args(param_types, args)?;
+        self.types.decode_args(param_types, &encoded)
+    }
+
+    /// Returns the SHA-256 hash of the Sierra program, in hexadecimal, identifying the version
+    /// of the program a failure was found in.
+    pub fn program_hash(&self) -> String {
+        format!(
+            "{:x}",
+            Sha256::digest(self.artifact.program.to_string().as_bytes())
+        )
+    }
+
+    /// Runs the function named `function_name` with the arguments given as a JSON array, and
+    /// returns the felts it returned. Fails with a [`CairoPanic`] if the function panicked.
+    pub fn call(&self, function_name: &str, args: &str) -> Result<Vec<Felt>> {
+        debug!("Running Cairo function: {}", function_name);
+        let function = self.function(Some(function_name))?;
+
+        let runner_args = self.encode_args(function, args)?;
+
+        success_values(self.run(function, &runner_args)?)
+    }
+
+    /// Runs the function named `function_name` once per JSON array of arguments, in parallel.
+    ///
+    /// Fails only if the function can't be found. Otherwise, returns one result per argument set,
+    /// in input order, each failing if its arguments are invalid or the function panicked.
+    pub fn call_batch(&self, function_name: &str, args: &[&str]) -> Result<Vec<Result<Vec<Felt>>>> {
+        debug!(
+            "Running Cairo function: {} over {} argument sets",
+            function_name,
+            args.len()
+        );
+        let function = self.function(Some(function_name))?;
+
+        Ok(args
+            .par_iter()
+            .map(|args| {
+                let runner_args = self.encode_args(function, args)?;
+                success_values(self.run(function, &runner_args)?)
+            })
+            .collect())
+    }
+
+    /// Same as [`CairoProgram::call`], decoding the returned felts according to the function's
+    /// return type.
+    pub fn call_value(&self, function_name: &str, args: &str) -> Result<CairoValue> {
+        debug!("Running Cairo function: {}", function_name);
+        let function = self.function(Some(function_name))?;
+
+        let runner_args = self.encode_args(function, args)?;
+        let run = self.run(function, &runner_args)?;
+        let memory = run.result.memory.clone();
+
+        self.decode_return_value(function, &success_values(run)?, &memory)
+    }
+
+    /// Same as [`CairoProgram::call_value`], with arguments given as values, e.g. generated by
+    /// [`CairoProgram::args_strategy`].
+    pub fn call_values(&self, function_name: &str, args: &[CairoValue]) -> Result<CairoValue> {
+        debug!("Running Cairo function: {}", function_name);
+        let function = self.function(Some(function_name))?;
+
+        let args = serde_json::Value::Array(args.iter().map(CairoValue::to_json).collect());
+        let runner_args = self
+            .types
+            .encode_args(&function.signature.param_types, &args)?;
+        let run = self.run(function, &runner_args)?;
+        let memory = run.result.memory.clone();
+
+        self.decode_return_value(function, &success_values(run)?, &memory)
+    }
+
+    /// Returns a strategy generating valid arguments for the function named `function_name`,
+    /// to be passed to [`CairoProgram::call_values`].
+    pub fn args_strategy(
+        &self,
+        function_name: &str,
+        config: &StrategyConfig,
+    ) -> Result<BoxedStrategy<Vec<CairoValue>>> {
+        let function = self.function(Some(function_name))?;
+        self.types
+            .args_strategy(&function.signature.param_types, config)
+    }
+
+    /// Decodes the felts returned by a successful run of `function`, reading arrays and boxes
+    /// from the run `memory`.
+    pub fn decode_return_value(
+        &self,
+        function: &Function,
+        values: &[Felt],
+        memory: &[Option<Felt>],
+    ) -> Result<CairoValue> {
+        match self.types.run_result_type(&function.signature.ret_types)? {
+            Some(ty) => self.types.decode(ty, values, memory),
+            None => Ok(CairoValue::Tuple(vec![])),
+        }
+    }
+
+    /// Decodes `event` as a value of the struct or enum `type_name`, found by
+    /// [`SierraTypes::find_type`].
+    ///
+    /// The event must have been emitted through a variant of a contract's `Event` enum named after
+    /// the type, which adds the variant selector as first key. The other keys followed by the data
+    /// are deserialized as the type's members, which assumes its `#[key]` members come first.
+    pub fn decode_event(&self, event: &Event, type_name: &str) -> Result<CairoValue> {
+        let ty = self.types.find_type(type_name)?;
+        let full_name = self.types.user_type_name(ty)?;
+        let name = full_name.rsplit("::").next().unwrap_or(&full_name);
+        ensure!(event.is_named(name), "not a {full_name} event: {event}");
+        let felts = [&event.keys[1..], &event.data].concat();
+        self.types.deserialize(ty, &felts)
+    }
+
+    /// Fails with an [`EventNotEmitted`] error unless one of `events` decodes as `type_name` to
+    /// `fields`, its value given as JSON, e.g. `[1, 2, "100"]` for a struct of three members.
+    ///
+    /// See [`CairoProgram::decode_event`] for how events are decoded, and [`SierraTypes::encode`]
+    /// for the JSON representation of each type.
+    pub fn assert_emitted(&self, events: &[Event], type_name: &str, fields: &str) -> Result<()> {
+        let ty = self.types.find_type(type_name)?;
+        let fields: serde_json::Value = serde_json::from_str(fields)
+            .with_context(|| format!("failed to parse event fields: {fields}"))?;
+        let encoded = self
+            .types
+            .encode(ty, &fields)
+            .with_context(|| format!("invalid {type_name} event fields"))?;
+        let Some(expected) = self
+            .types
+            .decode_args(std::slice::from_ref(ty), &encoded)?
+            .pop()
+        else {
+            bail!("invalid {type_name} event fields");
+        };
+
+        // Events of other types, or not decoding as this type, are skipped.
+        let emitted = events
+            .iter()
+            .filter_map(|event| self.decode_event(event, type_name).ok())
+            .collect::<Vec<_>>();
+        if emitted.contains(&expected) {
+            return Ok(());
+        }
+        Err(EventNotEmitted {
+            type_name: self.types.user_type_name(ty)?,
+            expected,
+            emitted,
+        }
+        .into())
+    }
+
+    /// Same as [`CairoProgram::call`], decoding the returned felts as a `T`.
+    pub fn call_typed<T: CairoDecode>(&self, function_name: &str, args: &str) -> Result<T> {
+        debug!("Running Cairo function: {}", function_name);
+        let function = self.function(Some(function_name))?;
+
+        let runner_args = self.encode_args(function, args)?;
+        let run = self.run(function, &runner_args)?;
+        let memory = run.result.memory.clone();
+
+        decode_felts(success_values(run)?, &memory)
+            .context("Failed to decode function result to the expected type")
+    }
+
+    /// Runs the function named `function_name` with Rust arguments, and decodes the returned felts
+    /// as a `T`. Pass several arguments as a tuple, e.g. `(values.as_slice(), 3u128)`.
+    pub fn call_with<T: CairoDecode>(
+        &self,
+        function_name: &str,
+        args: impl CairoEncode,
+    ) -> Result<T> {
+        debug!("Running Cairo function: {}", function_name);
+        let function = self.function(Some(function_name))?;
+
+        let run = self.run(function, &encode_args(&args))?;
+        let memory = run.result.memory.clone();
+
+        decode_felts(success_values(run)?, &memory)
+            .context("Failed to decode function result to the expected type")
+    }
+
+    /// Same as [`CairoProgram::call`], returning the VM resources used by the run instead of the
+    /// returned felts.
+    pub fn call_resources(&self, function_name: &str, args: &str) -> Result<ExecutionResources> {
+        debug!("Running Cairo function: {}", function_name);
+        let function = self.function(Some(function_name))?;
+
+        let runner_args = self.encode_args(function, args)?;
+        let run = self.run(function, &runner_args)?;
+        let resources = ExecutionResources::from(&run.result.used_resources);
+
+        success_values(run)?;
+        Ok(resources)
+    }
+
+    /// Same as [`CairoProgram::call_value`], rendering the call and its returned value for a
+    /// snapshot file.
+    pub fn snapshot(&self, function_name: &str, args: &str) -> Result<Snapshot> {
+        let function = self.function(Some(function_name))?;
+        let value = self.call_value(function_name, args)?;
+        // Normalize the arguments so that reformatting them doesn't change the snapshot.
+        let args = serde_json::from_str::<serde_json::Value>(args)
+            .with_context(|| format!("invalid arguments: {args}"))?;
+
+        Ok(Snapshot {
+            function: function.id.to_string(),
+            args: args.to_string(),
+            value: format!("{value:#}"),
+        })
+    }
+
+    /// Runs the function named `function_name` and compares its returned value with the snapshot
+    /// file at `snapshot_path`, updating it according to [`SnapshotUpdate::from_env`].
+    pub fn assert_snapshot(
+        &self,
+        snapshot_path: &Path,
+        function_name: &str,
+        args: &str,
+    ) -> Result<()> {
+        let snapshot = self.snapshot(function_name, args)?;
+        assert_snapshot(snapshot_path, &snapshot, SnapshotUpdate::from_env()?)
+    }
+}
+
+/// Returns the values returned by a successful run, or fails with a [`CairoPanic`] holding what
+/// the function printed if the run panicked.
+fn success_values(run: CairoRun) -> Result<Vec<Felt>> {
+    match run.result.value {
+        RunResultValue::Success(values) => Ok(values),
+        RunResultValue::Panic(values) => {
+            Err(CairoPanic::new(values).with_output(run.output).into())
+        }
+    }
+}
+
+/// Reads and deserializes the Sierra program JSON file at `sierra_path`.
+pub fn load_artifact(sierra_path: &Path) -> Result<ProgramArtifact> {
+    debug!("Loading Sierra program: {}", sierra_path.display());
+    let sierra_program = fs::read_to_string(sierra_path)
+        .with_context(|| format!("failed to read Sierra file: {}", sierra_path.display()))?;
+    let sierra_program: VersionedProgram =
+        serde_json::from_str(&sierra_program).with_context(|| {
+            format!(
+                "failed to deserialize Sierra program: {}",
+                sierra_path.display()
+            )
+        })?;
+    sierra_program
+        .into_v1()
+        .with_context(|| format!("failed to load Sierra program: {}", sierra_path.display()))
+}
+
+fn main_function<'a>(
+    runner: &'a SierraCasmRunner,
+    sierra_program: &'a ProgramArtifact,
+    name: Option<&str>,
+) -> Result<&'a Function> {
+    let executables = sierra_program
+        .debug_info
+        .as_ref()
+        .and_then(|di| di.executables.get(EXECUTABLE_NAME))
+        .cloned()
+        .unwrap_or_default();
+
+    // Prioritize `--function` args. First search among executables, then among all functions.
+    if let Some(name) = name {
+        // A fully qualified name, e.g. `my_package::tests::test_foo`, designates a single function.
+        if let Some(function) = sierra_program
+            .program
+            .funcs
+            .iter()
+            .find(|function| function.id.debug_name.as_deref() == Some(name))
+        {
+            return Ok(function);
+        }
+        let name = format!("::{name}");
+        return executables
+            .iter()
+            .find(|fid| {
+                fid.debug_name
+                    .as_deref()
+                    .map(|debug_name| debug_name.ends_with(&name))
+                    .unwrap_or_default()
+            })
+            .map(|fid| find_function(sierra_program, fid))
+            .unwrap_or_else(|| Ok(runner.find_function(&name)?));
+    }
+
+    // Then check if executables are unambiguous.
+    if executables.len() == 1 {
+        return find_function(
+            sierra_program,
+            executables.first().expect("executables can't be empty"),
+        );
+    }
+
+    // If executables are ambiguous, bail with error.
+    if executables.len() > 1 {
+        let names = executables
+            .iter()
+            .flat_map(|fid| fid.debug_name.clone())
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        let msg = if names.is_empty() {
+            "please only mark a single function as executable or enable debug ids and choose function by name".to_string()
+        } else {
+            format!(
+                "please choose a function to run from the list:\n`{}`",
+                names.join("`, `")
+            )
+        };
+        bail!("multiple executable functions found\n{msg}");
+    }
+
+    // Finally check default function.
+    Ok(runner.find_function(DEFAULT_MAIN_FUNCTION)?)
+}
+
+fn find_function<'a>(
+    sierra_program: &'a ProgramArtifact,
+    fid: &FunctionId,
+) -> Result<&'a Function> {
+    sierra_program
+        .program
+        .funcs
+        .iter()
+        .find(|f| f.id == *fid)
+        .ok_or_else(|| anyhow!("Function not found: {}", fid.to_string()))
+}
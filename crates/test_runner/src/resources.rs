@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use cairo_lang_runner::StarknetExecutionResources;
+use serde::Serialize;
+
+/// The builtins always listed in [`ExecutionResources::builtins`], even when unused.
+pub const REPORTED_BUILTINS: [&str; 6] = [
+    "range_check",
+    "pedersen",
+    "poseidon",
+    "bitwise",
+    "ec_op",
+    "segment_arena",
+];
+
+/// The Cairo VM resources used by a run, which determine its proving cost.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ExecutionResources {
+    /// The number of VM steps.
+    pub steps: usize,
+    /// The number of memory cells left unused between used cells.
+    pub memory_holes: usize,
+    /// The number of instances of each builtin used, by name, e.g. `range_check`.
+    pub builtins: BTreeMap<String, usize>,
+    /// The number of calls to each syscall, by name.
+    pub syscalls: BTreeMap<String, usize>,
+}
+
+impl ExecutionResources {
+    /// Returns the number of instances of the builtin `name` used, e.g. `range_check`.
+    pub fn builtin(&self, name: &str) -> usize {
+        self.builtins.get(name).copied().unwrap_or_default()
+    }
+}
+
+impl From<&StarknetExecutionResources> for ExecutionResources {
+    fn from(resources: &StarknetExecutionResources) -> Self {
+        let basic_resources = &resources.basic_resources;
+        let mut builtins = REPORTED_BUILTINS
+            .iter()
+            .map(|name| (name.to_string(), 0))
+            .collect::<BTreeMap<_, _>>();
+        builtins.extend(
+            basic_resources
+                .builtin_instance_counter
+                .iter()
+                .map(|(name, count)| (name.to_str().to_string(), *count)),
+        );
+        Self {
+            steps: basic_resources.n_steps,
+            memory_holes: basic_resources.n_memory_holes,
+            builtins,
+            syscalls: resources
+                .syscalls
+                .iter()
+                .map(|(name, count)| (name.clone(), *count))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionResources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Steps: {}", self.steps)?;
+        write!(f, "Memory holes: {}", self.memory_holes)?;
+        for (name, count) in &self.builtins {
+            write!(f, "\n{name}: {count}")?;
+        }
+        for (name, count) in &self.syscalls {
+            write!(f, "\n{name} syscalls: {count}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+
+/// The suffix of the snapshot files looked up by [`pending_snapshots`].
+pub const SNAPSHOT_SUFFIX: &str = ".snap";
+/// The suffix appended to a snapshot file for its pending replacement, e.g. `foo.snap.new` for
+/// `foo.snap`.
+const PENDING_SUFFIX: &str = ".new";
+
+/// The value returned by a function call, rendered for a snapshot file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The fully qualified name of the function.
+    pub function: String,
+    /// The arguments of the call, as a JSON array.
+    pub args: String,
+    /// The returned value, in the multi-line format of [`crate::value::CairoValue`].
+    pub value: String,
+}
+
+impl Snapshot {
+    /// Formats the snapshot as stored in its file: a header identifying the call, followed by the
+    /// returned value.
+    pub fn to_file_content(&self) -> String {
+        format!(
+            "---\nfunction: {}\narguments: {}\n---\n{}\n",
+            self.function, self.args, self.value
+        )
+    }
+}
+
+/// How [`assert_snapshot`] handles a snapshot file that is missing or doesn't match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SnapshotUpdate {
+    /// Write the new snapshot next to the stored one, as `<name>.snap.new`, to be reviewed and
+    /// accepted with [`accept_snapshots`].
+    #[default]
+    New,
+    /// Overwrite the stored snapshot, without failing.
+    Always,
+    /// Write nothing, e.g. on CI.
+    No,
+}
+
+impl SnapshotUpdate {
+    /// The environment variable selecting the update mode: `new`, `always` or `no`.
+    pub const ENV_VAR: &'static str = "CAIRO_SNAPSHOT_UPDATE";
+
+    /// Returns the update mode set with [`SnapshotUpdate::ENV_VAR`], `new` by default.
+    pub fn from_env() -> Result<Self> {
+        match env::var(Self::ENV_VAR) {
+            Ok(mode) => mode
+                .parse()
+                .with_context(|| format!("invalid {}", Self::ENV_VAR)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+impl FromStr for SnapshotUpdate {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode {
+            "new" => Ok(Self::New),
+            "always" => Ok(Self::Always),
+            "no" => Ok(Self::No),
+            _ => Err(anyhow!(
+                "unknown snapshot update mode `{mode}`, expected `new`, `always` or `no`"
+            )),
+        }
+    }
+}
+
+/// A snapshot file that is missing or doesn't match the value returned by the call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotMismatch {
+    pub path: PathBuf,
+    /// Whether the snapshot file doesn't exist yet.
+    pub missing: bool,
+    /// The file the new snapshot was written to for review, if any.
+    pub pending: Option<PathBuf>,
+    /// The changes from the stored snapshot to the new one, as a unified diff.
+    pub diff: String,
+}
+
+impl fmt::Display for SnapshotMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = if self.missing {
+            "does not exist"
+        } else {
+            "does not match"
+        };
+        writeln!(f, "snapshot {} {problem}:", self.path.display())?;
+        write!(f, "{}", self.diff)?;
+        match &self.pending {
+            Some(pending) => write!(
+                f,
+                "the new snapshot was written to {}, accept it with `accept_snapshots` or \
+                 rerun with {}=always",
+                pending.display(),
+                SnapshotUpdate::ENV_VAR
+            ),
+            None => write!(
+                f,
+                "rerun with {}=always to update it",
+                SnapshotUpdate::ENV_VAR
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotMismatch {}
+
+/// Compares `snapshot` with the snapshot file at `path`, failing with a [`SnapshotMismatch`] if
+/// it is missing or different, unless `update` is [`SnapshotUpdate::Always`].
+pub fn assert_snapshot(path: &Path, snapshot: &Snapshot, update: SnapshotUpdate) -> Result<()> {
+    let content = snapshot.to_file_content();
+    let stored = match fs::read_to_string(path) {
+        Ok(stored) => Some(stored.replace("\r\n", "\n")),
+        Err(_) if !path.exists() => None,
+        Err(e) => {
+            return Err(e).with_context(|| format!("failed to read snapshot: {}", path.display()))
+        }
+    };
+    let pending = pending_path(path);
+
+    if stored.as_deref() == Some(content.as_str()) {
+        // A pending snapshot left by a previous mismatch is stale now.
+        if pending.exists() {
+            remove_file(&pending)?;
+        }
+        return Ok(());
+    }
+
+    let pending = match update {
+        SnapshotUpdate::Always => {
+            write_file(path, &content)?;
+            if pending.exists() {
+                remove_file(&pending)?;
+            }
+            return Ok(());
+        }
+        SnapshotUpdate::New => {
+            write_file(&pending, &content)?;
+            Some(pending)
+        }
+        SnapshotUpdate::No => None,
+    };
+    let diff = diffy::create_patch(stored.as_deref().unwrap_or_default(), &content);
+    Err(SnapshotMismatch {
+        path: path.to_path_buf(),
+        missing: stored.is_none(),
+        pending,
+        diff: diff.to_string(),
+    }
+    .into())
+}
+
+/// Returns the pending snapshots under `dir`, written by mismatching assertions on `.snap` files.
+/// Hidden directories are skipped.
+pub fn pending_snapshots(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut pending = Vec::new();
+    if dir.exists() {
+        collect_pending(dir, &mut pending)?;
+    }
+    pending.sort();
+    Ok(pending)
+}
+
+/// Replaces the snapshots under `dir` with their pending versions, and returns the updated
+/// snapshot files.
+pub fn accept_snapshots(dir: &Path) -> Result<Vec<PathBuf>> {
+    pending_snapshots(dir)?
+        .into_iter()
+        .map(|pending| {
+            let path = pending.with_extension("");
+            fs::rename(&pending, &path)
+                .with_context(|| format!("failed to accept snapshot: {}", pending.display()))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Deletes the pending snapshots under `dir`, and returns the deleted files.
+pub fn reject_snapshots(dir: &Path) -> Result<Vec<PathBuf>> {
+    let pending = pending_snapshots(dir)?;
+    for path in &pending {
+        remove_file(path)?;
+    }
+    Ok(pending)
+}
+
+fn pending_path(path: &Path) -> PathBuf {
+    let mut pending = path.as_os_str().to_owned();
+    pending.push(PENDING_SUFFIX);
+    PathBuf::from(pending)
+}
+
+fn collect_pending(dir: &Path, pending: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read directory: {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+        if path.is_dir() && !hidden {
+            collect_pending(&path, pending)?;
+        } else if path
+            .to_str()
+            .is_some_and(|path| path.ends_with(&format!("{SNAPSHOT_SUFFIX}{PENDING_SUFFIX}")))
+        {
+            pending.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(path, content)
+        .with_context(|| format!("failed to write snapshot: {}", path.display()))
+}
+
+fn remove_file(path: &Path) -> Result<()> {
+    fs::remove_file(path).with_context(|| format!("failed to remove snapshot: {}", path.display()))
+}
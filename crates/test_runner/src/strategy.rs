@@ -0,0 +1,201 @@
+use anyhow::{bail, Result};
+use cairo_lang_sierra::ids::ConcreteTypeId;
+use cairo_lang_sierra::program::ConcreteTypeLongId;
+use num_bigint::{BigInt, BigUint};
+use num_traits::Zero;
+use proptest::prelude::*;
+use proptest::strategy::{BoxedStrategy, Union};
+use starknet_types_core::felt::Felt;
+
+use crate::value::{CairoValue, SierraTypes, BOOL, BYTE_ARRAY, SPAN_PREFIX, TUPLE, U256};
+
+/// Bounds of the values generated by [`SierraTypes::strategy`].
+#[derive(Debug, Clone)]
+pub struct StrategyConfig {
+    /// The maximum length of generated arrays and spans.
+    pub max_array_len: usize,
+    /// The maximum length of generated `ByteArray`s, in bytes.
+    pub max_string_len: usize,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self {
+            max_array_len: 10,
+            max_string_len: 64,
+        }
+    }
+}
+
+impl SierraTypes {
+    /// Returns a strategy generating the arguments of a function taking `param_types`, one value
+    /// per non-builtin parameter.
+    pub fn args_strategy(
+        &self,
+        param_types: &[ConcreteTypeId],
+        config: &StrategyConfig,
+    ) -> Result<BoxedStrategy<Vec<CairoValue>>> {
+        Ok(param_types
+            .iter()
+            .filter(|ty| !self.is_builtin(ty))
+            .map(|ty| self.strategy(ty, config))
+            .collect::<Result<Vec<_>>>()?
+            .boxed())
+    }
+
+    /// Returns a strategy generating any valid value of type `ty`: integers within the bounds of
+    /// their type, felts below the field prime, arrays of up to `config.max_array_len` elements
+    /// and any variant of enums.
+    pub fn strategy(
+        &self,
+        ty: &ConcreteTypeId,
+        config: &StrategyConfig,
+    ) -> Result<BoxedStrategy<CairoValue>> {
+        let long_id = self.long_id(ty)?;
+        Ok(match long_id.generic_id.0.as_str() {
+            "felt252" | "ContractAddress" | "ClassHash" | "StorageAddress"
+            | "StorageBaseAddress" => any::<[u8; 32]>()
+                .prop_map(|bytes| CairoValue::Felt(Felt::from_bytes_be(&bytes)))
+                .boxed(),
+            "bytes31" => any::<[u8; 31]>()
+                .prop_map(|bytes| CairoValue::Felt(Felt::from_bytes_be_slice(&bytes)))
+                .boxed(),
+            "u8" => uint_strategy(any::<u8>()),
+            "u16" => uint_strategy(any::<u16>()),
+            "u32" => uint_strategy(any::<u32>()),
+            "u64" => uint_strategy(any::<u64>()),
+            "u128" => uint_strategy(any::<u128>()),
+            "i8" => int_strategy(any::<i8>()),
+            "i16" => int_strategy(any::<i16>()),
+            "i32" => int_strategy(any::<i32>()),
+            "i64" => int_strategy(any::<i64>()),
+            "i128" => int_strategy(any::<i128>()),
+            "Snapshot" => self.strategy(self.inner_type(long_id)?, config)?,
+            "NonZero" => self
+                .strategy(self.inner_type(long_id)?, config)?
+                .prop_filter("non-zero value", |value| !is_zero(value))
+                .boxed(),
+            "Array" => prop::collection::vec(
+                self.strategy(self.inner_type(long_id)?, config)?,
+                0..=config.max_array_len,
+            )
+            .prop_map(CairoValue::Array)
+            .boxed(),
+            "Struct" => self.struct_strategy(ty, long_id, config)?,
+            "Enum" => self.enum_strategy(ty, long_id, config)?,
+            _ => bail!("Unsupported argument type: {}", self.name(ty)),
+        })
+    }
+
+    fn struct_strategy(
+        &self,
+        ty: &ConcreteTypeId,
+        long_id: &ConcreteTypeLongId,
+        config: &StrategyConfig,
+    ) -> Result<BoxedStrategy<CairoValue>> {
+        let name = self.user_type_name(ty)?;
+        let members = self.type_args(long_id).collect::<Vec<_>>();
+
+        Ok(match name.as_str() {
+            U256 => (any::<u128>(), any::<u128>())
+                .prop_map(|(low, high)| {
+                    CairoValue::Uint(BigUint::from(low) + (BigUint::from(high) << 128))
+                })
+                .boxed(),
+            BYTE_ARRAY => {
+                let max_len = config.max_string_len;
+                prop::collection::vec(any::<char>(), 0..=max_len)
+                    .prop_map(move |chars| {
+                        // Characters take up to 4 bytes: keep those within `max_len` bytes.
+                        let mut string = String::new();
+                        for c in chars {
+                            if string.len() + c.len_utf8() > max_len {
+                                break;
+                            }
+                            string.push(c);
+                        }
+                        CairoValue::String(string)
+                    })
+                    .boxed()
+            }
+            name if name.starts_with(SPAN_PREFIX) && members.len() == 1 => {
+                self.strategy(members[0], config)?
+            }
+            _ => {
+                let fields = members
+                    .into_iter()
+                    .map(|member| self.strategy(member, config))
+                    .collect::<Result<Vec<_>>>()?;
+                if name == TUPLE {
+                    fields.prop_map(CairoValue::Tuple).boxed()
+                } else {
+                    let member_names = self.member_names(ty, fields.len()).map(<[String]>::to_vec);
+                    fields
+                        .prop_map(move |fields| CairoValue::Struct {
+                            name: name.clone(),
+                            members: member_names.clone(),
+                            fields,
+                        })
+                        .boxed()
+                }
+            }
+        })
+    }
+
+    fn enum_strategy(
+        &self,
+        ty: &ConcreteTypeId,
+        long_id: &ConcreteTypeLongId,
+        config: &StrategyConfig,
+    ) -> Result<BoxedStrategy<CairoValue>> {
+        let name = self.user_type_name(ty)?;
+        if name == BOOL {
+            return Ok(any::<bool>().prop_map(CairoValue::Bool).boxed());
+        }
+
+        let variants = self
+            .type_args(long_id)
+            .enumerate()
+            .map(|(variant, variant_type)| {
+                let name = name.clone();
+                Ok(self
+                    .strategy(variant_type, config)?
+                    .prop_map(move |value| CairoValue::Enum {
+                        name: name.clone(),
+                        variant,
+                        value: Box::new(value),
+                    })
+                    .boxed())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if variants.is_empty() {
+            bail!("Can't generate values of empty enum {}", self.name(ty));
+        }
+        Ok(Union::new(variants).boxed())
+    }
+}
+
+fn uint_strategy<T: Into<BigUint>>(
+    strategy: impl Strategy<Value = T> + 'static,
+) -> BoxedStrategy<CairoValue> {
+    strategy
+        .prop_map(|value| CairoValue::Uint(value.into()))
+        .boxed()
+}
+
+fn int_strategy<T: Into<BigInt>>(
+    strategy: impl Strategy<Value = T> + 'static,
+) -> BoxedStrategy<CairoValue> {
+    strategy
+        .prop_map(|value| CairoValue::Int(value.into()))
+        .boxed()
+}
+
+fn is_zero(value: &CairoValue) -> bool {
+    match value {
+        CairoValue::Felt(value) => *value == Felt::ZERO,
+        CairoValue::Uint(value) => value.is_zero(),
+        CairoValue::Int(value) => value.is_zero(),
+        _ => false,
+    }
+}
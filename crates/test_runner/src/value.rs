@@ -0,0 +1,812 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::zip;
+
+use anyhow::{anyhow, bail, ensure, Result};
+use cairo_lang_runner::Arg;
+use cairo_lang_sierra::ids::ConcreteTypeId;
+use cairo_lang_sierra::program::{ConcreteTypeLongId, GenericArg, ProgramArtifact};
+use cairo_lang_utils::byte_array::BYTES_IN_WORD;
+use itertools::Itertools;
+use num_bigint::{BigInt, BigUint};
+use num_traits::ToPrimitive;
+use starknet_types_core::felt::Felt;
+
+use crate::encode::encode_variant;
+
+/// Generic ids of the builtins a function may take and return as implicits.
+const BUILTIN_GENERIC_IDS: [&str; 11] = [
+    "RangeCheck",
+    "RangeCheck96",
+    "GasBuiltin",
+    "Bitwise",
+    "EcOp",
+    "Pedersen",
+    "Poseidon",
+    "System",
+    "SegmentArena",
+    "AddMod",
+    "MulMod",
+];
+
+const PANIC_RESULT_PREFIX: &str = "core::panics::PanicResult::";
+pub(crate) const SPAN_PREFIX: &str = "core::array::Span::";
+pub(crate) const U256: &str = "core::integer::u256";
+pub(crate) const BYTE_ARRAY: &str = "core::byte_array::ByteArray";
+pub(crate) const BOOL: &str = "core::bool";
+pub(crate) const TUPLE: &str = "Tuple";
+
+/// A Cairo value, decoded from felts according to its Sierra type.
+///
+/// Sierra programs do not record struct member or enum variant names, so fields and variants are
+/// identified by position, unless the member names are given, e.g. by a contract ABI: see
+/// [`SierraTypes::with_member_names`]. Structs without member names are displayed like tuple
+/// structs, e.g. `MyStruct(1, 2)`.
+///
+/// The alternate format, `{:#}`, spreads arrays, tuples and structs over several lines, one
+/// element per line, which keeps diffs of large values readable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CairoValue {
+    /// A `felt252`, or any other single felt type without a more specific representation, such as
+    /// `ContractAddress` or `bytes31`.
+    Felt(Felt),
+    /// An unsigned integer, including `u256`.
+    Uint(BigUint),
+    /// A signed integer.
+    Int(BigInt),
+    Bool(bool),
+    /// A `ByteArray`.
+    String(String),
+    /// An `Array` or a `Span`.
+    Array(Vec<CairoValue>),
+    Tuple(Vec<CairoValue>),
+    Struct {
+        name: String,
+        /// The names of the fields, if known.
+        members: Option<Vec<String>>,
+        fields: Vec<CairoValue>,
+    },
+    Enum {
+        name: String,
+        variant: usize,
+        value: Box<CairoValue>,
+    },
+    /// A value of a type that can't be decoded structurally, as its raw felts.
+    Raw {
+        type_name: String,
+        felts: Vec<Felt>,
+    },
+}
+
+impl fmt::Display for CairoValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CairoValue::Felt(value) => write!(f, "{value}"),
+            CairoValue::Uint(value) => write!(f, "{value}"),
+            CairoValue::Int(value) => write!(f, "{value}"),
+            CairoValue::Bool(value) => write!(f, "{value}"),
+            CairoValue::String(value) => write!(f, "{value:?}"),
+            CairoValue::Array(values) if f.alternate() && !values.is_empty() => write_block(
+                f,
+                "[",
+                values.iter().map(|value| (String::new(), value)),
+                "]",
+            ),
+            CairoValue::Array(values) => {
+                write!(f, "[")?;
+                write_separated(f, values)?;
+                write!(f, "]")
+            }
+            CairoValue::Tuple(values) if f.alternate() && !values.is_empty() => write_block(
+                f,
+                "(",
+                values.iter().map(|value| (String::new(), value)),
+                ")",
+            ),
+            CairoValue::Tuple(values) => {
+                write!(f, "(")?;
+                write_separated(f, values)?;
+                if values.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            CairoValue::Struct {
+                name,
+                members: Some(members),
+                fields,
+            } if f.alternate() && !fields.is_empty() => {
+                let fields =
+                    zip(members, fields).map(|(member, field)| (format!("{member}: "), field));
+                write_block(f, &format!("{name} {{"), fields, "}")
+            }
+            CairoValue::Struct {
+                name,
+                members: Some(members),
+                fields,
+            } => {
+                write!(f, "{name} {{")?;
+                for (i, (member, field)) in zip(members, fields).enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{separator}{member}: {field}")?;
+                }
+                if !fields.is_empty() {
+                    write!(f, " ")?;
+                }
+                write!(f, "}}")
+            }
+            CairoValue::Struct { name, fields, .. } if f.alternate() && !fields.is_empty() => {
+                let fields = fields.iter().map(|field| (String::new(), field));
+                write_block(f, &format!("{name}("), fields, ")")
+            }
+            CairoValue::Struct { name, fields, .. } => {
+                write!(f, "{name}(")?;
+                write_separated(f, fields)?;
+                write!(f, ")")
+            }
+            CairoValue::Enum {
+                name,
+                variant,
+                value,
+            } if f.alternate() => write!(f, "{name}[{variant}]({value:#})"),
+            CairoValue::Enum {
+                name,
+                variant,
+                value,
+            } => write!(f, "{name}[{variant}]({value})"),
+            CairoValue::Raw { type_name, felts } => {
+                write!(f, "{type_name}<")?;
+                write_separated(f, felts)?;
+                write!(f, ">")
+            }
+        }
+    }
+}
+
+/// Writes `items` one per line between `open` and `close`, each preceded by its prefix and
+/// indented, in the alternate format.
+fn write_block<'a>(
+    f: &mut fmt::Formatter<'_>,
+    open: &str,
+    items: impl Iterator<Item = (String, &'a CairoValue)>,
+    close: &str,
+) -> fmt::Result {
+    writeln!(f, "{open}")?;
+    for (prefix, item) in items {
+        let item = format!("{item:#}").replace('\n', "\n    ");
+        writeln!(f, "    {prefix}{item},")?;
+    }
+    write!(f, "{close}")
+}
+
+fn write_separated<T: fmt::Display>(f: &mut fmt::Formatter<'_>, values: &[T]) -> fmt::Result {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{value}")?;
+    }
+    Ok(())
+}
+
+/// The type declarations of a Sierra program, used to decode values from their felts.
+pub struct SierraTypes {
+    declarations: HashMap<ConcreteTypeId, ConcreteTypeLongId>,
+    names: HashMap<ConcreteTypeId, String>,
+    /// The member names of structs, by user type name.
+    member_names: HashMap<String, Vec<String>>,
+}
+
+impl SierraTypes {
+    /// Collects the type declarations of `artifact`.
+    pub fn new(artifact: &ProgramArtifact) -> Self {
+        let declarations = artifact
+            .program
+            .type_declarations
+            .iter()
+            .map(|declaration| (declaration.id.clone(), declaration.long_id.clone()))
+            .collect();
+        let names = artifact
+            .program
+            .type_declarations
+            .iter()
+            .filter_map(|declaration| {
+                let name = declaration.id.debug_name.clone().or_else(|| {
+                    artifact
+                        .debug_info
+                        .as_ref()
+                        .and_then(|di| di.type_names.get(&declaration.id).cloned())
+                })?;
+                Some((declaration.id.clone(), name.to_string()))
+            })
+            .collect();
+
+        Self {
+            declarations,
+            names,
+            member_names: HashMap::new(),
+        }
+    }
+
+    /// Names the members of the structs of `member_names`, keyed by their full type name, e.g.
+    /// `erc20::Transfer`. Sierra programs don't record them, but contract ABIs do.
+    pub fn with_member_names(mut self, member_names: HashMap<String, Vec<String>>) -> Self {
+        self.member_names = member_names;
+        self
+    }
+
+    /// Returns the member names of the struct `ty` of `n_members` members, if known.
+    pub(crate) fn member_names(&self, ty: &ConcreteTypeId, n_members: usize) -> Option<&[String]> {
+        let names = self.member_names.get(&self.user_type_name(ty).ok()?)?;
+        (names.len() == n_members).then_some(names.as_slice())
+    }
+
+    /// Returns the debug name of `ty`, or its id if the program has no debug info.
+    pub fn name(&self, ty: &ConcreteTypeId) -> String {
+        self.names
+            .get(ty)
+            .cloned()
+            .unwrap_or_else(|| ty.to_string())
+    }
+
+    /// Returns the declaration of `ty`.
+    pub fn long_id(&self, ty: &ConcreteTypeId) -> Result<&ConcreteTypeLongId> {
+        self.declarations
+            .get(ty)
+            .ok_or_else(|| anyhow!("Unknown type: {}", ty))
+    }
+
+    /// Returns whether `ty` is a builtin passed to and returned from functions as an implicit.
+    pub fn is_builtin(&self, ty: &ConcreteTypeId) -> bool {
+        self.long_id(ty)
+            .map(|long_id| BUILTIN_GENERIC_IDS.contains(&long_id.generic_id.0.as_str()))
+            .unwrap_or_default()
+    }
+
+    /// Returns the type of the value returned by a successful run of a function with the given
+    /// return types: the function's last non-builtin return type, unwrapped from its panic
+    /// wrapper. `None` stands for the unit type.
+    pub fn run_result_type<'a>(
+        &'a self,
+        ret_types: &'a [ConcreteTypeId],
+    ) -> Result<Option<&'a ConcreteTypeId>> {
+        let Some(ty) = ret_types.last().filter(|ty| !self.is_builtin(ty)) else {
+            return Ok(None);
+        };
+        if !self.user_type_name(ty)?.starts_with(PANIC_RESULT_PREFIX) {
+            return Ok(Some(ty));
+        }
+
+        // `PanicResult<(T,)>` holds the `(T,)` tuple in its first variant.
+        let [_, GenericArg::Type(tuple), _] = self.long_id(ty)?.generic_args.as_slice() else {
+            bail!("Unexpected panic wrapper type: {}", self.name(ty));
+        };
+        match self.long_id(tuple)?.generic_args.as_slice() {
+            [_] => Ok(None),
+            [_, GenericArg::Type(inner)] => Ok(Some(inner)),
+            _ => Ok(Some(tuple)),
+        }
+    }
+
+    /// Returns the size of `ty`, in felts.
+    pub fn size(&self, ty: &ConcreteTypeId) -> Result<usize> {
+        let long_id = self.long_id(ty)?;
+        Ok(match long_id.generic_id.0.as_str() {
+            "Struct" => self
+                .type_args(long_id)
+                .map(|member| self.size(member))
+                .sum::<Result<usize>>()?,
+            "Enum" => {
+                1 + self
+                    .type_args(long_id)
+                    .map(|variant| self.size(variant))
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .max()
+                    .unwrap_or_default()
+            }
+            "Snapshot" | "NonZero" | "Uninitialized" => self.size(self.inner_type(long_id)?)?,
+            "Array" | "EcPoint" => 2,
+            "EcState" => 3,
+            _ => 1,
+        })
+    }
+
+    /// Decodes a value of type `ty` from `felts`, reading arrays and boxes from `memory`.
+    pub fn decode(
+        &self,
+        ty: &ConcreteTypeId,
+        felts: &[Felt],
+        memory: &[Option<Felt>],
+    ) -> Result<CairoValue> {
+        let mut cursor = felts;
+        let value = self.decode_next(ty, &mut cursor, memory)?;
+        ensure!(
+            cursor.is_empty(),
+            "{} felts left after decoding a value of type {}",
+            cursor.len(),
+            self.name(ty)
+        );
+        Ok(value)
+    }
+
+    /// Decodes the arguments of a function taking `param_types`, as encoded by
+    /// [`SierraTypes::encode_args`], into one value per non-builtin parameter.
+    pub fn decode_args(
+        &self,
+        param_types: &[ConcreteTypeId],
+        args: &[Arg],
+    ) -> Result<Vec<CairoValue>> {
+        // Lay the arrays out in memory, to decode them like the arrays returned by a run.
+        let mut memory = vec![];
+        let felts = flatten_args(args, &mut memory);
+        let mut cursor = felts.as_slice();
+        let values = param_types
+            .iter()
+            .filter(|ty| !self.is_builtin(ty))
+            .map(|ty| self.decode_next(ty, &mut cursor, &memory))
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(
+            cursor.is_empty(),
+            "{} felts left after decoding the arguments",
+            cursor.len()
+        );
+        Ok(values)
+    }
+
+    /// Decodes a value of type `ty` from its `Serde` serialization, e.g. the keys and data of an
+    /// event: arrays are prefixed by their length, and enums by their variant index.
+    pub fn deserialize(&self, ty: &ConcreteTypeId, felts: &[Felt]) -> Result<CairoValue> {
+        let mut values = self.deserialize_values(std::slice::from_ref(ty), felts)?;
+        Ok(values.remove(0))
+    }
+
+    /// Decodes one value per type of `types` from their consecutive `Serde` serializations, e.g.
+    /// the data returned by a contract entry point.
+    pub fn deserialize_values(
+        &self,
+        types: &[ConcreteTypeId],
+        felts: &[Felt],
+    ) -> Result<Vec<CairoValue>> {
+        let mut cursor = felts;
+        let mut args = vec![];
+        for ty in types {
+            self.deserialize_next(ty, &mut cursor, &mut args)?;
+        }
+        ensure!(
+            cursor.is_empty(),
+            "{} felts left after deserializing values of types {}",
+            cursor.len(),
+            types.iter().map(|ty| self.name(ty)).join(", ")
+        );
+        // Lay the arrays out in memory, to decode them like the arrays returned by a run.
+        let mut memory = vec![];
+        let felts = flatten_args(&args, &mut memory);
+        let mut cursor = felts.as_slice();
+        types
+            .iter()
+            .map(|ty| self.decode_next(ty, &mut cursor, &memory))
+            .collect()
+    }
+
+    /// Returns the struct or enum named `name`, either its full path, e.g. `erc20::Transfer`, or
+    /// its last segment if no other type shares it.
+    pub fn find_type(&self, name: &str) -> Result<&ConcreteTypeId> {
+        let suffix = format!("::{name}");
+        let mut matches = self
+            .declarations
+            .iter()
+            .filter(|(_, long_id)| matches!(long_id.generic_id.0.as_str(), "Struct" | "Enum"))
+            .filter_map(|(ty, _)| Some((ty, self.user_type_name(ty).ok()?)))
+            .filter(|(_, type_name)| type_name == name || type_name.ends_with(&suffix))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(_, a), (_, b)| a.cmp(b));
+        match matches.as_slice() {
+            [] => bail!("No struct or enum named {name}"),
+            [(ty, _)] => Ok(ty),
+            _ => match matches.iter().find(|(_, type_name)| type_name == name) {
+                Some((ty, _)) => Ok(ty),
+                None => bail!(
+                    "Ambiguous type name {name}, matching {}",
+                    matches.iter().map(|(_, type_name)| type_name).join(", ")
+                ),
+            },
+        }
+    }
+
+    /// Returns the type named `name` in a contract ABI, e.g. `core::integer::u256`,
+    /// `core::array::Array::<core::felt252>` or `(core::felt252, core::bool)`.
+    pub fn find_abi_type(&self, name: &str) -> Result<&ConcreteTypeId> {
+        self.declarations
+            .keys()
+            .filter(|ty| self.has_abi_name(ty, name))
+            .min_by_key(|ty| ty.id)
+            .ok_or_else(|| anyhow!("No type named {name} in the program"))
+    }
+
+    /// Returns whether `ty` is named `name` in contract ABIs, which name types by their full path,
+    /// including builtin types such as `core::felt252`.
+    fn has_abi_name(&self, ty: &ConcreteTypeId, name: &str) -> bool {
+        let Ok(long_id) = self.long_id(ty) else {
+            return false;
+        };
+        let inner_has_name = |inner_name: &str| {
+            self.inner_type(long_id)
+                .is_ok_and(|inner| self.has_abi_name(inner, inner_name))
+        };
+        match long_id.generic_id.0.as_str() {
+            "Struct" | "Enum" => {
+                let Ok(type_name) = self.user_type_name(ty) else {
+                    return false;
+                };
+                match name
+                    .strip_prefix('(')
+                    .and_then(|name| name.strip_suffix(')'))
+                {
+                    Some(members) if type_name == TUPLE => {
+                        let members = split_type_list(members);
+                        let member_types = self.type_args(long_id).collect::<Vec<_>>();
+                        members.len() == member_types.len()
+                            && zip(members, member_types)
+                                .all(|(name, ty)| self.has_abi_name(ty, name))
+                    }
+                    _ => type_name == name,
+                }
+            }
+            "Array" => name
+                .strip_prefix("core::array::Array::<")
+                .and_then(|name| name.strip_suffix('>'))
+                .is_some_and(inner_has_name),
+            "Snapshot" => name.strip_prefix('@').is_some_and(inner_has_name),
+            generic_id => {
+                long_id.generic_args.is_empty() && name.rsplit("::").next() == Some(generic_id)
+            }
+        }
+    }
+
+    /// Converts the `Serde` serialization of a value of type `ty`, from the start of `felts`, to
+    /// runner arguments laid out like the value, advancing past it.
+    fn deserialize_next(
+        &self,
+        ty: &ConcreteTypeId,
+        felts: &mut &[Felt],
+        args: &mut Vec<Arg>,
+    ) -> Result<()> {
+        let long_id = self.long_id(ty)?;
+        match long_id.generic_id.0.as_str() {
+            "Snapshot" | "NonZero" => {
+                self.deserialize_next(self.inner_type(long_id)?, felts, args)?
+            }
+            "Struct" => {
+                for member in self.type_args(long_id) {
+                    self.deserialize_next(member, felts, args)?;
+                }
+            }
+            "Array" => {
+                let element = self.inner_type(long_id)?;
+                let len = take_felt(felts)?
+                    .to_usize()
+                    .ok_or_else(|| anyhow!("Invalid array length for {}", self.name(ty)))?;
+                let mut elements = vec![];
+                for _ in 0..len {
+                    self.deserialize_next(element, felts, &mut elements)?;
+                }
+                args.push(Arg::Array(elements));
+            }
+            "Enum" => {
+                let variants = self.type_args(long_id).collect::<Vec<_>>();
+                let variant = take_felt(felts)?
+                    .to_usize()
+                    .filter(|variant| *variant < variants.len())
+                    .ok_or_else(|| anyhow!("Invalid variant index for {}", self.name(ty)))?;
+                let padding = self.size(ty)? - 1 - self.size(variants[variant])?;
+                encode_variant(args, variants.len(), variant, padding);
+                self.deserialize_next(variants[variant], felts, args)?;
+            }
+            _ if self.size(ty)? == 1 => args.push(Arg::Value(take_felt(felts)?)),
+            _ => bail!("{} can't be deserialized", self.name(ty)),
+        }
+        Ok(())
+    }
+
+    /// Decodes a value of type `ty` from the start of `felts`, advancing past it.
+    fn decode_next(
+        &self,
+        ty: &ConcreteTypeId,
+        felts: &mut &[Felt],
+        memory: &[Option<Felt>],
+    ) -> Result<CairoValue> {
+        let long_id = self.long_id(ty)?;
+        let generic_id = long_id.generic_id.0.as_str();
+        Ok(match generic_id {
+            "felt252" | "bytes31" | "ContractAddress" | "ClassHash" | "StorageAddress"
+            | "StorageBaseAddress" => CairoValue::Felt(take_felt(felts)?),
+            "u8" | "u16" | "u32" | "u64" | "u128" => {
+                CairoValue::Uint(take_felt(felts)?.to_biguint())
+            }
+            "i8" | "i16" | "i32" | "i64" | "i128" => CairoValue::Int(to_signed(&take_felt(felts)?)),
+            "Snapshot" | "NonZero" => self.decode_next(self.inner_type(long_id)?, felts, memory)?,
+            "Box" => {
+                let inner = self.inner_type(long_id)?;
+                let start = to_address(&take_felt(felts)?)?;
+                let inner_felts = read_memory(memory, start, start + self.size(inner)?)?;
+                self.decode(inner, &inner_felts, memory)?
+            }
+            "Array" => {
+                let element = self.inner_type(long_id)?;
+                let start = to_address(&take_felt(felts)?)?;
+                let end = to_address(&take_felt(felts)?)?;
+                let element_felts = read_memory(memory, start, end)?;
+                let mut cursor = element_felts.as_slice();
+                let mut elements = vec![];
+                while !cursor.is_empty() {
+                    elements.push(self.decode_next(element, &mut cursor, memory)?);
+                }
+                CairoValue::Array(elements)
+            }
+            "Struct" => self.decode_struct(ty, long_id, felts, memory)?,
+            "Enum" => self.decode_enum(ty, long_id, felts, memory)?,
+            _ => {
+                let size = self.size(ty)?;
+                ensure!(
+                    felts.len() >= size,
+                    "Not enough felts to decode {}",
+                    self.name(ty)
+                );
+                let (raw, rest) = felts.split_at(size);
+                *felts = rest;
+                CairoValue::Raw {
+                    type_name: self.name(ty),
+                    felts: raw.to_vec(),
+                }
+            }
+        })
+    }
+
+    fn decode_struct(
+        &self,
+        ty: &ConcreteTypeId,
+        long_id: &ConcreteTypeLongId,
+        felts: &mut &[Felt],
+        memory: &[Option<Felt>],
+    ) -> Result<CairoValue> {
+        let fields = self
+            .type_args(long_id)
+            .map(|member| self.decode_next(member, felts, memory))
+            .collect::<Result<Vec<_>>>()?;
+        let name = self.user_type_name(ty)?;
+
+        Ok(match (name.as_str(), fields.as_slice()) {
+            (U256, [CairoValue::Uint(low), CairoValue::Uint(high)]) => {
+                CairoValue::Uint(low + (high << 128))
+            }
+            (
+                BYTE_ARRAY,
+                [CairoValue::Array(words), pending_word, CairoValue::Uint(pending_len)],
+            ) => match decode_byte_array(words, pending_word, pending_len) {
+                Some(string) => CairoValue::String(string),
+                None => self.struct_value(ty, name, fields),
+            },
+            (TUPLE, _) => CairoValue::Tuple(fields),
+            (name, [array]) if name.starts_with(SPAN_PREFIX) => array.clone(),
+            _ => self.struct_value(ty, name, fields),
+        })
+    }
+
+    /// Returns the value of the struct `ty` named `name`, with its member names if known.
+    fn struct_value(
+        &self,
+        ty: &ConcreteTypeId,
+        name: String,
+        fields: Vec<CairoValue>,
+    ) -> CairoValue {
+        CairoValue::Struct {
+            members: self.member_names(ty, fields.len()).map(<[String]>::to_vec),
+            name,
+            fields,
+        }
+    }
+
+    fn decode_enum(
+        &self,
+        ty: &ConcreteTypeId,
+        long_id: &ConcreteTypeLongId,
+        felts: &mut &[Felt],
+        memory: &[Option<Felt>],
+    ) -> Result<CairoValue> {
+        let variants = self.type_args(long_id).collect::<Vec<_>>();
+        let payload_size = self.size(ty)? - 1;
+        ensure!(
+            felts.len() > payload_size,
+            "Not enough felts to decode {}",
+            self.name(ty)
+        );
+
+        let selector = take_felt(felts)?
+            .to_usize()
+            .ok_or_else(|| anyhow!("Invalid variant selector for {}", self.name(ty)))?;
+        let variant = variant_index(variants.len(), selector)
+            .ok_or_else(|| anyhow!("Invalid variant selector for {}", self.name(ty)))?;
+        let variant_type = variants[variant];
+
+        // Variants smaller than the largest one are padded with leading zeros.
+        let (payload, rest) = felts.split_at(payload_size);
+        *felts = rest;
+        let padding = payload_size - self.size(variant_type)?;
+        let value = self.decode(variant_type, &payload[padding..], memory)?;
+
+        let name = self.user_type_name(ty)?;
+        if name == BOOL {
+            return Ok(CairoValue::Bool(variant == 1));
+        }
+        Ok(CairoValue::Enum {
+            name,
+            variant,
+            value: Box::new(value),
+        })
+    }
+
+    /// Returns the user type name of a struct or enum, e.g. `core::integer::u256`.
+    pub(crate) fn user_type_name(&self, ty: &ConcreteTypeId) -> Result<String> {
+        match self.long_id(ty)?.generic_args.first() {
+            Some(GenericArg::UserType(user_type)) => Ok(user_type
+                .debug_name
+                .as_ref()
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| user_type.to_string())),
+            _ => Ok(self.name(ty)),
+        }
+    }
+
+    /// Returns the type generic arguments of `long_id`, e.g. the members of a struct.
+    pub(crate) fn type_args<'a>(
+        &self,
+        long_id: &'a ConcreteTypeLongId,
+    ) -> impl Iterator<Item = &'a ConcreteTypeId> {
+        long_id.generic_args.iter().filter_map(|arg| match arg {
+            GenericArg::Type(ty) => Some(ty),
+            _ => None,
+        })
+    }
+
+    /// Returns the wrapped type of a generic type with a single type argument, e.g. `Box<T>`.
+    pub(crate) fn inner_type<'a>(
+        &self,
+        long_id: &'a ConcreteTypeLongId,
+    ) -> Result<&'a ConcreteTypeId> {
+        self.type_args(long_id)
+            .next()
+            .ok_or_else(|| anyhow!("Missing type argument for {}", long_id.generic_id))
+    }
+}
+
+/// Returns the index of the variant with the given selector, out of `n_variants`.
+///
+/// Inverse of `get_variant_selector` in `cairo-lang-sierra-to-casm`: enums with more than two
+/// variants use a relative jump as selector.
+pub(crate) fn variant_index(n_variants: usize, selector: usize) -> Option<usize> {
+    if n_variants <= 2 {
+        return (selector < n_variants).then_some(selector);
+    }
+    if selector % 2 == 0 || selector >= 2 * n_variants {
+        return None;
+    }
+    Some(n_variants - (selector + 1) / 2)
+}
+
+/// Splits a comma-separated list of type names, e.g. the members of a tuple type, ignoring the
+/// commas nested in generic arguments and tuples.
+fn split_type_list(list: &str) -> Vec<&str> {
+    let mut names = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in list.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                names.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = list[start..].trim();
+    if !last.is_empty() {
+        names.push(last);
+    }
+    names
+}
+
+/// Flattens `args` into felts, replacing each array by the start and end addresses of its
+/// elements, which are appended to `memory`.
+fn flatten_args(args: &[Arg], memory: &mut Vec<Option<Felt>>) -> Vec<Felt> {
+    let mut felts = vec![];
+    for arg in args {
+        match arg {
+            Arg::Value(value) => felts.push(*value),
+            Arg::Array(elements) => {
+                let elements = flatten_args(elements, memory);
+                let start = memory.len();
+                memory.extend(elements.into_iter().map(Some));
+                felts.push(Felt::from(start));
+                felts.push(Felt::from(memory.len()));
+            }
+        }
+    }
+    felts
+}
+
+fn take_felt(felts: &mut &[Felt]) -> Result<Felt> {
+    let (first, rest) = felts
+        .split_first()
+        .ok_or_else(|| anyhow!("Not enough felts to decode the value"))?;
+    *felts = rest;
+    Ok(*first)
+}
+
+fn to_address(felt: &Felt) -> Result<usize> {
+    felt.to_usize()
+        .ok_or_else(|| anyhow!("Invalid memory address: {}", felt))
+}
+
+fn read_memory(memory: &[Option<Felt>], start: usize, end: usize) -> Result<Vec<Felt>> {
+    memory
+        .get(start..end)
+        .ok_or_else(|| anyhow!("Memory range {}..{} out of bounds", start, end))?
+        .iter()
+        .map(|cell| cell.ok_or_else(|| anyhow!("Uninitialized memory cell")))
+        .collect()
+}
+
+/// Interprets `felt` as a signed integer, values above `P / 2` being negative.
+fn to_signed(felt: &Felt) -> BigInt {
+    let value = felt.to_biguint();
+    let prime = Felt::MAX.to_biguint() + 1u32;
+    if value > &prime / 2u32 {
+        BigInt::from(value) - BigInt::from(prime)
+    } else {
+        BigInt::from(value)
+    }
+}
+
+fn decode_byte_array(
+    words: &[CairoValue],
+    pending_word: &CairoValue,
+    pending_len: &BigUint,
+) -> Option<String> {
+    let words = words
+        .iter()
+        .map(|word| match word {
+            CairoValue::Felt(word) => Some(*word),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let CairoValue::Felt(pending_word) = pending_word else {
+        return None;
+    };
+    byte_array_string(&words, pending_word, pending_len.to_usize()?)
+}
+
+/// Decodes the UTF-8 string held by a `ByteArray`: `BYTES_IN_WORD` big-endian bytes per full word,
+/// followed by the last `pending_len` bytes of the pending word. Returns `None` if a word holds
+/// more bytes than that, or if the bytes aren't valid UTF-8.
+pub(crate) fn byte_array_string(
+    words: &[Felt],
+    pending_word: &Felt,
+    pending_len: usize,
+) -> Option<String> {
+    if pending_len >= BYTES_IN_WORD {
+        return None;
+    }
+    let mut bytes = vec![];
+    let words = words.iter().map(|word| (word, BYTES_IN_WORD));
+    for (word, len) in words.chain([(pending_word, pending_len)]) {
+        let word = word.to_bytes_be();
+        let (padding, word) = word.split_at(word.len() - len);
+        if padding.iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        bytes.extend_from_slice(word);
+    }
+    String::from_utf8(bytes).ok()
+}
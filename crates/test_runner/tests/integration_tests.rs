@@ -1,14 +1,46 @@
 use std::path::Path;
+use std::sync::OnceLock;
 
 use anyhow::Result;
+use cairo_lang_runner::RunResultValue;
+use cairo_lang_sierra::program::ProgramArtifact;
+use cairo_lang_sierra::ProgramParser;
+use cairo_lang_starknet_classes::contract_class::{
+    ContractClass as SierraContractClass, ContractEntryPoint, ContractEntryPoints,
+};
+use cairo_lang_utils::byte_array::BYTE_ARRAY_MAGIC;
+use starknet_types_core::felt::Felt;
+use test_runner::decode::{decode_felts, CairoDecode};
+use test_runner::encode::{encode_args, Arg, CairoEncode, U256};
+use test_runner::context::{read_storage, ExecutionContext};
+use test_runner::contract::{ContractClass, ContractEnv};
+use test_runner::corpus::FailureCorpus;
+use test_runner::differential::{DifferentialConfig, DifferentialMismatch};
+use test_runner::events::{selector, Event, EventNotEmitted, L2ToL1Message};
+use test_runner::gas::GasSnapshot;
 use test_runner::manual_types::MyStruct;
 use test_runner::manual_types::{Stack, U128, U32};
+use test_runner::panic::CairoPanic;
+use test_runner::program::{load_artifact, CairoProgram, Instrumentation};
+use test_runner::snapshot::{
+    accept_snapshots, assert_snapshot, pending_snapshots, reject_snapshots, SnapshotMismatch,
+    SnapshotUpdate,
+};
+use test_runner::strategy::StrategyConfig;
 use test_runner::test_utils::load_and_run_cairo_function;
+use test_runner::value::CairoValue;
 
 use proptest::prelude::*;
+use proptest::strategy::BoxedStrategy;
 
 const SIERRA_PATH: &str = "../../cairo_project/target/dev/sample_project.sierra.json";
 
+/// Compiles the sample program once for all the tests of this file.
+fn program() -> &'static CairoProgram {
+    static PROGRAM: OnceLock<CairoProgram> = OnceLock::new();
+    PROGRAM.get_or_init(|| CairoProgram::load(Path::new(SIERRA_PATH)).unwrap())
+}
+
 fn reference_bytes32_words(input: u128) -> u128 {
     (input + 31) / 32
 }
@@ -45,13 +77,673 @@ fn test_my_struct() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_my_struct_value() -> Result<()> {
+    let result = program().call_value("my_struct", "[1, 2]")?;
+    let expected = CairoValue::Struct {
+        name: "sample_project::utils::MyStruct".to_string(),
+        members: None,
+        fields: vec![CairoValue::Uint(1u32.into()), CairoValue::Uint(2u32.into())],
+    };
+    assert_eq!(expected, result);
+    assert_eq!(
+        "sample_project::utils::MyStruct(1, 2)",
+        result.to_string()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_byte_array_value() -> Result<()> {
+    let types = program().types();
+    let ty = types.find_type("ByteArray")?;
+    for string in ["héllo ✓", "a\0b", "a string longer than thirty-one bytes: déjà vu"] {
+        let args = types.encode(ty, &serde_json::json!(string))?;
+        let value = types.decode_args(std::slice::from_ref(ty), &args)?;
+        assert_eq!(vec![CairoValue::String(string.to_string())], value);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_stack_push_value() -> Result<()> {
+    let result = program().call_value("stack_push_should_add_element", "[[1, 2], 3]")?;
+    assert_eq!(CairoValue::String("[1, 2, 3]".to_string()), result);
+    Ok(())
+}
+
+#[test]
+fn test_struct_argument() -> Result<()> {
+    let result = program().call("extract_field_2", "[[1, 2]]")?;
+    assert_eq!(vec![Felt::from(2)], result);
+
+    // Sierra programs don't record member names.
+    let error = program()
+        .call("extract_field_2", r#"[{"field1": 1, "field2": 2}]"#)
+        .unwrap_err();
+    assert!(format!("{error:#}").contains("Sierra programs don't record member names"));
+    Ok(())
+}
+
+#[test]
+fn test_argument_mismatch() {
+    let error = program().call("extract_field_1", "[[1]]").unwrap_err();
+    assert!(format!("{error:#}").contains("Expected 2 fields"));
+
+    let error = program().call("my_struct", "[1, 4294967296]").unwrap_err();
+    assert!(format!("{error:#}").contains("4294967296 is out of range for type u32"));
+
+    let error = program().call("bytes32_words", "[[1]]").unwrap_err();
+    assert!(format!("{error:#}").contains("Expected an integer for type u128, got [1]"));
+}
+
+#[derive(Debug, PartialEq, Eq, CairoDecode, CairoEncode)]
+enum Shape {
+    Empty,
+    Point(u32, u32),
+    Named { id: u8 },
+}
+
+#[test]
+fn test_derive_decode_enum() -> Result<()> {
+    let felts = |values: [u32; 3]| values.map(Felt::from).to_vec();
+
+    // Enums with more than two variants use relative selectors: 5, 3, 1.
+    assert_eq!(Shape::Empty, decode_felts(felts([5, 0, 0]), &[])?);
+    assert_eq!(Shape::Point(7, 8), decode_felts(felts([3, 7, 8]), &[])?);
+    assert_eq!(Shape::Named { id: 9 }, decode_felts(felts([1, 0, 9]), &[])?);
+    assert!(decode_felts::<Shape>(felts([2, 0, 0]), &[]).is_err());
+    assert!(decode_felts::<Shape>(felts([1, 0, 256]), &[]).is_err());
+
+    let some: Option<MyStruct> = decode_felts(felts([0, 1, 2]), &[])?;
+    assert_eq!(Some(MyStruct { field_0: 1, field_1: 2 }), some);
+    let none: Option<MyStruct> = decode_felts(felts([1, 0, 0]), &[])?;
+    assert_eq!(None, none);
+    Ok(())
+}
+
+#[test]
+fn test_derive_encode_enum() {
+    let values = |args: Vec<Arg>| {
+        args.into_iter()
+            .map(|arg| match arg {
+                Arg::Value(value) => value,
+                Arg::Array(_) => panic!("unexpected array"),
+            })
+            .collect::<Vec<_>>()
+    };
+    let felts = |values: [u32; 3]| values.map(Felt::from).to_vec();
+
+    assert_eq!(felts([5, 0, 0]), values(encode_args(&Shape::Empty)));
+    assert_eq!(felts([3, 7, 8]), values(encode_args(&Shape::Point(7, 8))));
+    assert_eq!(felts([1, 0, 9]), values(encode_args(&Shape::Named { id: 9 })));
+
+    let u256 = U256 { low: 1, high: 2 };
+    assert_eq!(vec![Felt::ONE, Felt::TWO], values(encode_args(&u256)));
+    assert_eq!("680564733841876926926749214863536422913", u256.to_string());
+}
+
+#[test]
+fn test_string_round_trip() -> Result<()> {
+    for string in ["héllo ✓", "a\0b", "a string longer than thirty-one bytes: déjà vu"] {
+        let [Arg::Array(words), Arg::Value(pending_word), Arg::Value(pending_len)] =
+            <[Arg; 3]>::try_from(encode_args(&string.to_string())).unwrap()
+        else {
+            panic!("unexpected ByteArray encoding");
+        };
+        // Lay the words out in memory, as a run returns them.
+        let memory = words
+            .iter()
+            .map(|word| match word {
+                Arg::Value(word) => Some(*word),
+                Arg::Array(_) => panic!("unexpected array"),
+            })
+            .collect::<Vec<_>>();
+        let felts = vec![Felt::ZERO, Felt::from(memory.len()), pending_word, pending_len];
+        assert_eq!(string, decode_felts::<String>(felts, &memory)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_call_with_struct() -> Result<()> {
+    let my_struct = || MyStruct {
+        field_0: 1,
+        field_1: 2,
+    };
+    let result: u32 = program().call_with("extract_field_2", my_struct())?;
+    assert_eq!(2, result);
+
+    let result: MyStruct = program().call_with("my_struct", (1u128, 2u32))?;
+    assert_eq!(my_struct(), result);
+    Ok(())
+}
+
+#[test]
+fn test_decode_string() -> Result<()> {
+    let result: String = program().call_typed("stack_push_should_add_element", "[[1, 2], 3]")?;
+    assert_eq!("[1, 2, 3]", result);
+    Ok(())
+}
+
+#[test]
+fn test_panic() {
+    let error = program()
+        .call("stack_pop_should_return_last_element", "[[]]")
+        .unwrap_err();
+    let panic = error.downcast_ref::<CairoPanic>().unwrap();
+    assert_eq!(vec![Some("u32_sub Overflow".to_string())], panic.short_strings);
+    assert_eq!(None, panic.message);
+    assert_eq!("Function panicked: u32_sub Overflow", error.to_string());
+}
+
+#[test]
+fn test_panic_byte_array_message() {
+    // `panic!("Index out of bounds: {}", 3)`.
+    let felts = vec![
+        Felt::from_hex(BYTE_ARRAY_MAGIC).unwrap(),
+        Felt::ZERO,
+        Felt::from_bytes_be_slice(b"Index out of bounds: 3"),
+        Felt::from(22),
+    ];
+    let panic = CairoPanic::new(felts);
+    assert_eq!(Some("Index out of bounds: 3".to_string()), panic.message);
+    assert_eq!("Index out of bounds: 3", panic.reason());
+
+    // `panic!("déjà vu")`.
+    let felts = vec![
+        Felt::from_hex(BYTE_ARRAY_MAGIC).unwrap(),
+        Felt::ZERO,
+        Felt::from_bytes_be_slice("déjà vu".as_bytes()),
+        Felt::from("déjà vu".len()),
+    ];
+    let panic = CairoPanic::new(felts);
+    assert_eq!(Some("déjà vu".to_string()), panic.message);
+}
+
+#[test]
+fn test_gas_snapshot() -> Result<()> {
+    let program = program();
+    let function = program.function(Some("stack_push_should_add_element"))?;
+    let args = program.encode_args(function, "[[1, 2], 3]")?;
+    let gas = program
+        .gas_consumed(&program.run(function, &args)?.result)
+        .expect("gas metering is enabled");
+    let name = function.id.to_string();
+
+    let mut snapshot = GasSnapshot::default();
+    snapshot.record(&name, &args, gas);
+    assert_eq!(Some(gas), snapshot.get(&name, "[[1, 2], 3]"));
+    assert!(snapshot.compare(&snapshot, 0.0).diffs.is_empty());
+
+    let mut baseline = GasSnapshot::default();
+    baseline.record(&name, &args, gas - 10);
+    assert!(snapshot.compare(&baseline, 0.0).has_regressions());
+    assert!(!snapshot.compare(&baseline, 50.0).has_regressions());
+    assert!(!baseline.compare(&snapshot, 0.0).has_regressions());
+
+    let path = std::env::temp_dir().join("test_gas_snapshot.json");
+    snapshot.save(&path)?;
+    assert_eq!(snapshot, GasSnapshot::load(&path)?);
+    Ok(())
+}
+
+#[test]
+fn test_execution_resources() -> Result<()> {
+    let resources = program().call_resources("stack_push_should_add_element", "[[1, 2], 3]")?;
+    assert!(resources.steps > 0);
+    assert!(resources.builtin("range_check") > 0);
+    assert_eq!(0, resources.builtin("pedersen"));
+    assert!(resources.builtins.contains_key("poseidon"));
+
+    let more_resources =
+        program().call_resources("stack_push_should_add_element", "[[1, 2, 3, 4], 5]")?;
+    assert!(more_resources.steps > resources.steps);
+    Ok(())
+}
+
+#[test]
+fn test_coverage() -> Result<()> {
+    // The sample program is built without code locations: map each statement to its own line.
+    let mut artifact = load_artifact(Path::new(SIERRA_PATH))?;
+    let n_statements = artifact.program.statements.len();
+    let locations = (0..n_statements)
+        .map(|i| {
+            let span = serde_json::json!({
+                "start": { "line": i, "col": 0 },
+                "end": { "line": i, "col": 1 },
+            });
+            (i.to_string(), serde_json::json!([["/src/lib.cairo", span]]))
+        })
+        .collect::<serde_json::Map<_, _>>();
+    artifact.debug_info.as_mut().unwrap().annotations.insert(
+        "github.com/software-mansion/cairo-coverage".to_string(),
+        serde_json::json!({ "statements_code_locations": locations }),
+    );
+
+    let program = CairoProgram::with_coverage(artifact, Some(usize::MAX))?;
+    program.call("my_struct", "[1, 2]")?;
+    let report = program.coverage_report()?;
+    let lines = report.file("/src/lib.cairo").unwrap();
+    assert_eq!(n_statements, lines.len());
+    let hit = lines.values().filter(|steps| **steps > 0).count();
+    assert!(0 < hit && hit < n_statements);
+
+    let lcov = report.to_lcov();
+    assert!(lcov.starts_with("SF:/src/lib.cairo\nDA:1,"));
+    assert!(lcov.ends_with(&format!("LF:{n_statements}\nLH:{hit}\nend_of_record\n")));
+
+    let mut without_core = report.clone();
+    without_core.retain_under(Path::new("/project"));
+    assert!(without_core.file("/src/lib.cairo").is_none());
+    Ok(())
+}
+
+#[test]
+fn test_coverage_requires_code_locations() -> Result<()> {
+    let program = CairoProgram::with_coverage(load_artifact(Path::new(SIERRA_PATH))?, Some(usize::MAX))?;
+    program.call("my_struct", "[1, 2]")?;
+    let error = program.coverage_report().unwrap_err();
+    assert!(error.to_string().contains("no statements code locations"));
+    Ok(())
+}
+
+#[test]
+fn test_profile() -> Result<()> {
+    let instrumentation = Instrumentation {
+        profile: true,
+        ..Default::default()
+    };
+    let program = CairoProgram::instrumented(
+        load_artifact(Path::new(SIERRA_PATH))?,
+        Some(usize::MAX),
+        instrumentation,
+    )?;
+    let function = program.function(Some("stack_push_should_add_element"))?;
+    let args = program.encode_args(function, "[[1, 2], 3]")?;
+    program.run(function, &args)?;
+    let report = program.profile_report()?;
+
+    let functions = report.functions();
+    assert_eq!(
+        "sample_project::stack::tests::stack_push_should_add_element",
+        functions[0].name
+    );
+    assert!(functions[0].total_steps > 0);
+    assert_eq!(
+        functions[0].total_steps,
+        functions.iter().map(|function| function.self_steps).sum::<usize>()
+    );
+    assert!(report
+        .to_folded()
+        .lines()
+        .all(|line| line.starts_with("sample_project::stack::tests::stack_push_should_add_element")));
+    assert_eq!(2, report.top(2).0.len());
+    Ok(())
+}
+
+#[test]
+fn test_snapshots() -> Result<()> {
+    program().assert_snapshot(
+        Path::new("tests/snapshots/stack_push_should_add_element.snap"),
+        "stack_push_should_add_element",
+        "[[1, 2], 3]",
+    )?;
+    program().assert_snapshot(
+        Path::new("tests/snapshots/my_struct.snap"),
+        "my_struct",
+        "[1, 2]",
+    )
+}
+
+#[test]
+fn test_snapshot_review() -> Result<()> {
+    let dir = std::env::temp_dir().join("test_snapshot_review");
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = dir.join("my_struct.snap");
+
+    let snapshot = program().snapshot("my_struct", "[1, 2]")?;
+    let error = assert_snapshot(&path, &snapshot, SnapshotUpdate::New).unwrap_err();
+    let mismatch = error.downcast_ref::<SnapshotMismatch>().unwrap();
+    assert!(mismatch.missing);
+    assert_eq!(Some(dir.join("my_struct.snap.new")), mismatch.pending);
+    assert!(!path.exists());
+    assert_eq!(vec![dir.join("my_struct.snap.new")], pending_snapshots(&dir)?);
+
+    assert_eq!(vec![path.clone()], accept_snapshots(&dir)?);
+    assert!(pending_snapshots(&dir)?.is_empty());
+    assert_snapshot(&path, &snapshot, SnapshotUpdate::No)?;
+
+    let changed = program().snapshot("my_struct", "[1, 3]")?;
+    let error = assert_snapshot(&path, &changed, SnapshotUpdate::New).unwrap_err();
+    let mismatch = error.downcast_ref::<SnapshotMismatch>().unwrap();
+    assert!(mismatch.diff.contains("-arguments: [1,2]\n+arguments: [1,3]"));
+    assert_eq!(1, reject_snapshots(&dir)?.len());
+    assert_snapshot(&path, &snapshot, SnapshotUpdate::No)?;
+    Ok(())
+}
+
+#[test]
+fn test_bytes32_words_batch() -> Result<()> {
+    let inputs: Vec<u128> = (0..200).map(|i| i * 7).collect();
+    let args: Vec<String> = inputs.iter().map(|input| format!("[{}]", input)).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let results = program().call_batch("bytes32_words", &args)?;
+
+    assert_eq!(inputs.len(), results.len());
+    for (input, result) in inputs.into_iter().zip(results) {
+        assert_eq!(vec![Felt::from(reference_bytes32_words(input))], result?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_bytes32_words_differential() -> Result<()> {
+    let config = DifferentialConfig {
+        cases: 10,
+        ..Default::default()
+    };
+    program().differential_test("bytes32_words", &config, |args| {
+        let [CairoValue::Uint(input)] = args else {
+            panic!("unexpected arguments: {args:?}");
+        };
+        CairoValue::Uint((input + 31u32) / 32u32)
+    })
+}
+
+#[test]
+fn test_differential_mismatch() -> Result<()> {
+    // Saturates at 10 words: inputs above 320 differ, and shrink to the smallest of them.
+    let error = program()
+        .differential_test("bytes32_words", &DifferentialConfig::default(), |args| {
+            let [CairoValue::Uint(input)] = args else {
+                panic!("unexpected arguments: {args:?}");
+            };
+            CairoValue::Uint(((input + 31u32) / 32u32).min(10u32.into()))
+        })
+        .unwrap_err();
+    let mismatch = error.downcast_ref::<DifferentialMismatch>().unwrap();
+    assert_eq!("sample_project::utils::bytes32_words", mismatch.function);
+    assert_eq!(vec![CairoValue::Uint(321u32.into())], mismatch.args);
+    assert_eq!(("11", "10"), (mismatch.cairo.as_str(), mismatch.reference.as_str()));
+    assert_eq!(
+        "sample_project::utils::bytes32_words differs from its reference for input [321]\n  \
+         Cairo | Reference\n\
+         ! 11    | 10",
+        mismatch.to_string()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_differential_corpus() -> Result<()> {
+    let path = std::env::temp_dir().join("test_differential_corpus.json");
+    let _ = std::fs::remove_file(&path);
+    let saturating_reference = |args: &[CairoValue]| {
+        let [CairoValue::Uint(input)] = args else {
+            panic!("unexpected arguments: {args:?}");
+        };
+        CairoValue::Uint(((input + 31u32) / 32u32).min(10u32.into()))
+    };
+
+    let config = DifferentialConfig {
+        corpus: Some(path.clone()),
+        ..Default::default()
+    };
+    program()
+        .differential_test("bytes32_words", &config, saturating_reference)
+        .unwrap_err();
+    let corpus = FailureCorpus::load(&path)?;
+    let [case] = corpus.cases() else {
+        panic!("unexpected corpus: {corpus:?}");
+    };
+    assert_eq!("sample_project::utils::bytes32_words", case.function);
+    assert_eq!(program().program_hash(), case.program_hash);
+    assert_eq!(serde_json::json!(["321"]), case.args);
+    assert_eq!("11", case.result);
+
+    // The recorded case fails even when no input is generated.
+    let replay_only = DifferentialConfig { cases: 0, ..config };
+    let error = program()
+        .differential_test("bytes32_words", &replay_only, saturating_reference)
+        .unwrap_err();
+    let mismatch = error.downcast_ref::<DifferentialMismatch>().unwrap();
+    assert_eq!(vec![CairoValue::Uint(321u32.into())], mismatch.args);
+
+    let report = program().replay_corpus(&corpus);
+    assert!(report.0.iter().all(|replayed| replayed.reproduces() && !replayed.program_changed));
+    assert_eq!(
+        "Function                              Arguments  Recorded  Current\n\
+         sample_project::utils::bytes32_words  [\"321\"]    11        11",
+        report.to_string()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_decode_args() -> Result<()> {
+    let function = program().function(Some("stack_push_should_add_element"))?;
+    let args = program().decode_args(function, &serde_json::json!([[1, 2], 3]))?;
+    let uint = |value: u32| CairoValue::Uint(value.into());
+    assert_eq!(vec![CairoValue::Array(vec![uint(1), uint(2)]), uint(3)], args);
+    Ok(())
+}
+
+#[test]
+fn test_execution_context() -> Result<()> {
+    let context = ExecutionContext::default()
+        .with_block_number(1000)
+        .with_block_timestamp(1_700_000_000)
+        .with_caller_address(Felt::from(0x123))
+        .with_contract_address(Felt::from(0x456))
+        .with_chain_id(Felt::from_bytes_be_slice(b"SN_SEPOLIA"))
+        .with_storage(Felt::from(0x456), Felt::from(1), Felt::from(42));
+    let function = program().function(Some("bytes32_words"))?;
+    let args = program().encode_args(function, "[64]")?;
+    let result = program().run_in_context(function, &args, &context)?.result;
+
+    // The state is handed back unchanged by functions that don't use it.
+    let final_state = &result.starknet_state;
+    let mut expected = context.clone();
+    expected.storage.clear();
+    assert_eq!(expected, ExecutionContext::from_state(final_state)?);
+    assert_eq!(
+        Felt::from(42),
+        read_storage(final_state, Felt::from(0x456), Felt::from(1))?
+    );
+    assert_eq!(
+        Felt::ZERO,
+        read_storage(final_state, Felt::from(0x123), Felt::from(1))?
+    );
+    Ok(())
+}
+
+/// Loads a Sierra program written by hand, in the text format, from `tests/data`, with unlimited
+/// gas.
+fn load_sierra_text(name: &str) -> Result<CairoProgram> {
+    let sierra = std::fs::read_to_string(format!("tests/data/{name}"))?;
+    let sierra_program = ProgramParser::new()
+        .parse(&sierra)
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    CairoProgram::new(ProgramArtifact::stripped(sierra_program), Some(usize::MAX))
+}
+
+#[test]
+fn test_cheatcodes() -> Result<()> {
+    let program = load_sierra_text("cheatcodes.sierra")?;
+    let context = ExecutionContext::default().with_block_number(10);
+    let block_number = |function_name: &str| -> Result<u64> {
+        let function = program.function(Some(function_name))?;
+        let args = program.encode_args(function, "[1000]")?;
+        let result = program.run_in_context(function, &args, &context)?.result;
+        Ok(ExecutionContext::from_state(&result.starknet_state)?.block_number)
+    };
+    assert_eq!(1000, block_number("start_cheat_block_number")?);
+    assert_eq!(10, block_number("stop_cheat_block_number")?);
+
+    let function = program.function(Some("mocked_call"))?;
+    let result = program.run_in_context(function, &[], &context)?.result;
+    let RunResultValue::Success(values) = result.value else {
+        panic!("mocked_call panicked");
+    };
+    let [start, end] = values.as_slice() else {
+        panic!("unexpected return values: {values:?}");
+    };
+    let ret_data = (start.to_bigint().try_into()?..end.to_bigint().try_into()?)
+        .map(|address: usize| result.memory[address].unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![Felt::from(7), Felt::from(8)], ret_data);
+    assert_eq!(Some(&1), result.used_resources.syscalls.get("CallContract"));
+    Ok(())
+}
+
+#[test]
+fn test_events() -> Result<()> {
+    assert_eq!(
+        Felt::from_hex("0x99cd8bde557814842a3121e8ddfd433a539b8c9f14bf31ebf108d12e6196e9")?,
+        selector("Transfer")
+    );
+
+    let program = load_sierra_text("events.sierra")?;
+    let function = program.function(Some("emit_transfer"))?;
+    let context = ExecutionContext::default().with_contract_address(Felt::from(0x1234));
+    let run = program.run_in_context(function, &[], &context)?;
+    let transfer = Event {
+        from_address: Felt::from(0x1234),
+        keys: vec![selector("Transfer"), Felt::from(1), Felt::from(2)],
+        data: vec![Felt::from(100), Felt::ZERO],
+    };
+    assert_eq!(vec![transfer.clone()], run.events);
+    let message = L2ToL1Message {
+        from_address: Felt::from(0x1234),
+        to_address: Felt::from(42),
+        payload: vec![Felt::from(7)],
+    };
+    assert_eq!(vec![message], run.l2_to_l1_messages);
+
+    assert_eq!(
+        "test::Transfer(1, 2, 100)",
+        program.decode_event(&transfer, "Transfer")?.to_string()
+    );
+    program.assert_emitted(&run.events, "Transfer", "[1, 2, 100]")?;
+    let error = program
+        .assert_emitted(&run.events, "test::Transfer", "[1, 2, 101]")
+        .unwrap_err();
+    assert!(error.downcast_ref::<EventNotEmitted>().is_some());
+    Ok(())
+}
+
+#[test]
+fn test_print_output() -> Result<()> {
+    let program = load_sierra_text("print.sierra")?;
+    let function = program.function(Some("print_and_panic"))?;
+    let expected = "Hello!\n[DEBUG]\t0x1\n";
+    assert_eq!(expected, program.run(function, &[])?.output);
+
+    let error = program.call("print_and_panic", "[]").unwrap_err();
+    let panic = error.downcast_ref::<CairoPanic>().unwrap();
+    assert_eq!(expected, panic.output);
+    assert_eq!(
+        "Function panicked: oops\n\nCairo output:\nHello!\n[DEBUG]\t0x1",
+        error.to_string()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_contracts() -> Result<()> {
+    let caller = Felt::from(0x1234);
+    let mut env = ContractEnv::new(ExecutionContext::default().with_caller_address(caller))?;
+
+    let erc20 = ContractClass::load(Path::new("tests/data/erc20.contract_class.json"))?;
+    let erc20 = env.declare(erc20);
+    let token = env.deploy(erc20, r#"["0x544b4e", "0x544b4e", 18, "1000", "0x1234"]"#)?;
+    assert_eq!(
+        CairoValue::Uint(1000u32.into()),
+        env.call(token, "balance_of", r#"["0x1234"]"#)?
+    );
+    let transfer = env.invoke(token, "transfer", r#"["0x42", 300]"#)?;
+    env.assert_emitted(
+        token,
+        &transfer.run.events,
+        "Transfer",
+        r#"["0x1234", "0x42", 300]"#,
+    )?;
+    // Struct members are named after the ABI.
+    let event = env
+        .class(erc20)?
+        .program()
+        .decode_event(&transfer.run.events[0], "Transfer")?;
+    assert_eq!(
+        "test::erc_20::Transfer { from: 4660, to: 66, value: 300 }",
+        event.to_string()
+    );
+    assert_eq!(
+        CairoValue::Uint(700u32.into()),
+        env.call(token, "balance_of", r#"["0x1234"]"#)?
+    );
+    // Reverted calls leave the state as it was.
+    let error = env.call(token, "transfer", r#"["0x42", 701]"#).unwrap_err();
+    assert!(error.downcast_ref::<CairoPanic>().is_some());
+    assert_eq!(
+        CairoValue::Uint(300u32.into()),
+        env.call(token, "balance_of", r#"["0x42"]"#)?
+    );
+
+    let test_contract = Path::new("tests/data/test_contract.contract_class.json");
+    let test_contract = env.declare(ContractClass::load(test_contract)?);
+    let contract = env.deploy(test_contract, "[5]")?;
+    // Structs of the ABI are given by member name.
+    let test_contract = env.class(test_contract)?;
+    let calldata = test_contract.encode_calldata("another_function", r#"[{"a": 1, "b": true}]"#)?;
+    assert_eq!(vec![Felt::ONE, Felt::ONE], calldata);
+    let error = test_contract
+        .encode_calldata("another_function", r#"[{"a": 1}]"#)
+        .unwrap_err();
+    assert!(format!("{error:#}").contains("Missing member `b`"));
+    assert_eq!(Felt::from(5), env.storage(contract, selector("my_storage_var"))?);
+    // `test` returns its `ref` argument before the storage value it read, minus one.
+    let call = env.invoke(contract, "test", "[0, 0, 0]")?;
+    assert_eq!(vec![Felt::ZERO, Felt::from(5)], call.retdata);
+    assert_eq!(Felt::from(6), env.storage(contract, selector("my_storage_var"))?);
+
+    // `call_foo` calls `foo` on another contract, here one returning its calldata.
+    let echo = std::fs::read_to_string("tests/data/echo.sierra")?;
+    let echo = ProgramParser::new()
+        .parse(&echo)
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    let entry_points = ContractEntryPoints {
+        external: vec![ContractEntryPoint {
+            selector: selector("foo").to_biguint(),
+            function_idx: 0,
+        }],
+        ..Default::default()
+    };
+    let echo = SierraContractClass::new(&echo, entry_points, None, Default::default())?;
+    let echo = env.declare(ContractClass::new(echo)?);
+    let echo = env.deploy(echo, "[]")?;
+    let call_foo = format!(r#"["{echo:#x}", 7]"#);
+    assert_eq!(CairoValue::Uint(7u32.into()), env.call(contract, "call_foo", &call_foo)?);
+    let call = env.invoke(contract, "call_foo", r#"["0x404", 7]"#)?;
+    let RunResultValue::Panic(revert_reason) = call.run.result.value else {
+        panic!("call to an undeployed contract succeeded");
+    };
+    assert_eq!(
+        vec![Felt::from_bytes_be_slice(b"CONTRACT_NOT_DEPLOYED")],
+        revert_reason
+    );
+    Ok(())
+}
+
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(10))]
     #[test]
     fn test_bytes32_words_prop(input in 0u128..=u128::MAX) {
         let expected = reference_bytes32_words(input);
         let args = format!("[{}]", input);
-        let result = load_and_run_cairo_function::<U128>("bytes32_words", Path::new(SIERRA_PATH), &args).unwrap();
+        let result = program().call_typed::<U128>("bytes32_words", &args).unwrap();
 
         prop_assert_eq!(expected, *result);
     }
@@ -62,8 +754,9 @@ proptest! {
 
     #[test]
     fn test_stack_push(input in prop::collection::vec(0u128..=u128::MAX, 0..10), pushed_value in 0u128..=u128::MAX) {
-        let args = format!("[{:?}, {}]", input, pushed_value);
-        let result: Stack = load_and_run_cairo_function("stack_push_should_add_element", Path::new(SIERRA_PATH), &args).unwrap();
+        let result: Stack = program()
+            .call_with("stack_push_should_add_element", (input.as_slice(), pushed_value))
+            .unwrap();
 
         let mut expected_push = input.clone();
         expected_push.push(pushed_value);
@@ -74,8 +767,7 @@ proptest! {
 
     #[test]
     fn test_stack_pop(input in prop::collection::vec(0u128..=u128::MAX, 1..10)) {
-        let args = format!("[{:?}]", input);
-        let result: Stack = load_and_run_cairo_function("stack_pop_should_remove_last_element", Path::new(SIERRA_PATH), &args).unwrap();
+        let result: Stack = program().call_with("stack_pop_should_remove_last_element", &input[..]).unwrap();
 
         let mut expected = input.clone();
         expected.pop();
@@ -86,9 +778,54 @@ proptest! {
 
     #[test]
     fn test_stack_pop_return(input in prop::collection::vec(0u128..=u128::MAX, 1..10)) {
-        let args = format!("[{:?}]", input);
-        let result: U128 = load_and_run_cairo_function("stack_pop_should_return_last_element", Path::new(SIERRA_PATH), &args).unwrap();
+        let result: U128 = program().call_with("stack_pop_should_return_last_element", &input[..]).unwrap();
 
         prop_assert_eq!(result, U128(*input.last().unwrap()));
     }
 }
+
+fn args_strategy(function_name: &str) -> BoxedStrategy<Vec<CairoValue>> {
+    program()
+        .args_strategy(function_name, &StrategyConfig::default())
+        .unwrap()
+}
+
+fn byte_array_strategy() -> BoxedStrategy<CairoValue> {
+    let types = program().types();
+    let ty = types.find_type("ByteArray").unwrap();
+    types.strategy(ty, &StrategyConfig::default()).unwrap()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(10))]
+
+    #[test]
+    fn test_stack_push_any_input(args in args_strategy("stack_push_should_add_element")) {
+        let result = program().call_values("stack_push_should_add_element", &args).unwrap();
+
+        let [CairoValue::Array(input), pushed_value] = args.as_slice() else {
+            panic!("unexpected arguments: {args:?}");
+        };
+        let expected = input.iter().chain([pushed_value]).map(ToString::to_string).collect::<Vec<_>>();
+        prop_assert_eq!(result, CairoValue::String(format!("[{}]", expected.join(", "))));
+    }
+
+    #[test]
+    fn test_byte_array_any_value(value in byte_array_strategy()) {
+        let types = program().types();
+        let ty = types.find_type("ByteArray").unwrap();
+        let args = types.encode(ty, &value.to_json()).unwrap();
+        let decoded = types.decode_args(std::slice::from_ref(ty), &args).unwrap();
+        prop_assert_eq!(vec![value], decoded);
+    }
+
+    #[test]
+    fn test_my_struct_any_input(args in args_strategy("my_struct")) {
+        let result = program().call_values("my_struct", &args).unwrap();
+
+        let CairoValue::Struct { fields, .. } = result else {
+            panic!("unexpected result: {result}");
+        };
+        prop_assert_eq!(fields, args);
+    }
+}
@@ -0,0 +1,210 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Generics};
+
+/// Derives `test_runner::decode::CairoDecode` for a Rust mirror of a Cairo struct or enum.
+///
+/// Struct fields are decoded in declaration order, like the members of the Cairo struct. Enum
+/// variants must be declared in the same order as in Cairo, each variant's fields being decoded
+/// as its payload.
+#[proc_macro_derive(CairoDecode)]
+pub fn derive_cairo_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let trait_path = quote!(::test_runner::decode::CairoDecode);
+    let generics = add_trait_bounds(input.generics.clone(), &trait_path);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (size, decode) = match &input.data {
+        Data::Struct(data) => (
+            fields_size(&data.fields, &trait_path),
+            decode_fields(quote!(Self), &data.fields),
+        ),
+        Data::Enum(data) => {
+            let n_variants = data.variants.len();
+            let sizes = data
+                .variants
+                .iter()
+                .map(|variant| fields_size(&variant.fields, &trait_path))
+                .collect::<Vec<_>>();
+            let arms = data.variants.iter().enumerate().map(|(i, variant)| {
+                let ident = &variant.ident;
+                let decode = decode_fields(quote!(Self::#ident), &variant.fields);
+                quote!(#i => #decode)
+            });
+            (
+                enum_size(&sizes),
+                quote! {{
+                    let variant = reader.read_variant(#n_variants, ::std::any::type_name::<Self>())?;
+                    // Variants smaller than the largest one are padded with leading zeros.
+                    let sizes: [usize; #n_variants] = [#(#sizes),*];
+                    let variant_size = sizes[variant];
+                    reader.skip(<Self as #trait_path>::size() - 1 - variant_size)?;
+                    match variant {
+                        #(#arms,)*
+                        _ => unreachable!("variant index checked by `read_variant`"),
+                    }
+                }},
+            )
+        }
+        Data::Union(_) => {
+            return syn::Error::new_spanned(&input.ident, "CairoDecode can't be derived for unions")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    quote! {
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            fn size() -> usize {
+                #size
+            }
+
+            fn decode(
+                reader: &mut ::test_runner::decode::FeltReader<'_>,
+            ) -> ::std::result::Result<Self, ::test_runner::decode::DecodeError> {
+                ::std::result::Result::Ok(#decode)
+            }
+        }
+    }
+    .into()
+}
+
+/// Derives `test_runner::encode::CairoEncode` for a Rust mirror of a Cairo struct or enum.
+///
+/// Uses the same layout as `CairoDecode`: struct fields are encoded in declaration order, and
+/// enum variants must be declared in the same order as in Cairo.
+#[proc_macro_derive(CairoEncode)]
+pub fn derive_cairo_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let trait_path = quote!(::test_runner::encode::CairoEncode);
+    let generics = add_trait_bounds(input.generics.clone(), &trait_path);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (size, encode) = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, encode) = encode_fields(quote!(Self), &data.fields, &trait_path);
+            (
+                fields_size(&data.fields, &trait_path),
+                quote! {{
+                    let #pattern = self;
+                    #encode
+                }},
+            )
+        }
+        Data::Enum(data) => {
+            let n_variants = data.variants.len();
+            let sizes = data
+                .variants
+                .iter()
+                .map(|variant| fields_size(&variant.fields, &trait_path))
+                .collect::<Vec<_>>();
+            let arms = data.variants.iter().enumerate().map(|(i, variant)| {
+                let ident = &variant.ident;
+                let (pattern, encode) =
+                    encode_fields(quote!(Self::#ident), &variant.fields, &trait_path);
+                quote! {
+                    #pattern => {
+                        ::test_runner::encode::encode_variant(
+                            args,
+                            #n_variants,
+                            #i,
+                            <Self as #trait_path>::size() - 1 - sizes[#i],
+                        );
+                        #encode
+                    }
+                }
+            });
+            (
+                enum_size(&sizes),
+                quote! {{
+                    let sizes: [usize; #n_variants] = [#(#sizes),*];
+                    match self {
+                        #(#arms)*
+                    }
+                }},
+            )
+        }
+        Data::Union(_) => {
+            return syn::Error::new_spanned(&input.ident, "CairoEncode can't be derived for unions")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    quote! {
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            fn size() -> usize {
+                #size
+            }
+
+            fn encode(&self, args: &mut ::std::vec::Vec<::test_runner::encode::Arg>) {
+                #encode
+            }
+        }
+    }
+    .into()
+}
+
+/// Requires every type parameter to implement `trait_path`.
+fn add_trait_bounds(mut generics: Generics, trait_path: &TokenStream2) -> Generics {
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#trait_path));
+    }
+    generics
+}
+
+/// Returns the expression computing the total size of `fields`, in felts.
+fn fields_size(fields: &Fields, trait_path: &TokenStream2) -> TokenStream2 {
+    let types = fields.iter().map(|field| &field.ty);
+    quote!(0 #(+ <#types as #trait_path>::size())*)
+}
+
+/// Returns the expression computing the size of an enum with variants of the given sizes: its
+/// selector followed by its largest variant.
+fn enum_size(sizes: &[TokenStream2]) -> TokenStream2 {
+    let n_variants = sizes.len();
+    quote! {{
+        let sizes: [usize; #n_variants] = [#(#sizes),*];
+        1 + sizes.into_iter().max().unwrap_or_default()
+    }}
+}
+
+/// Returns the expression decoding `fields` in order and building them with `constructor`.
+fn decode_fields(constructor: TokenStream2, fields: &Fields) -> TokenStream2 {
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(#constructor { #(#names: reader.read()?,)* })
+        }
+        Fields::Unnamed(fields) => {
+            let reads = fields.unnamed.iter().map(|_| quote!(reader.read()?));
+            quote!(#constructor(#(#reads,)*))
+        }
+        Fields::Unit => constructor,
+    }
+}
+
+/// Returns a pattern binding `fields` of `constructor`, and the statements encoding them in order.
+fn encode_fields(
+    constructor: TokenStream2,
+    fields: &Fields,
+    trait_path: &TokenStream2,
+) -> (TokenStream2, TokenStream2) {
+    let bindings = fields
+        .iter()
+        .enumerate()
+        .map(|(i, _)| format_ident!("field_{}", i))
+        .collect::<Vec<_>>();
+    let pattern = match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(#constructor { #(#names: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(#constructor(#(#bindings),*)),
+        Fields::Unit => constructor,
+    };
+    (pattern, quote!(#(#trait_path::encode(#bindings, args);)*))
+}
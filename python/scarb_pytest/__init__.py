@@ -0,0 +1,37 @@
+from .scarb_pytest import (
+    CairoPanic,
+    ContractEnv,
+    CoverageReport,
+    Event,
+    ExecutionContext,
+    ExecutionResources,
+    GasReport,
+    GasSnapshot,
+    L2ToL1Message,
+    ProfileReport,
+    Program,
+    RunResult,
+    accept_snapshots,
+    pending_snapshots,
+    reject_snapshots,
+    run_scarb_pytest_py,
+)
+
+__all__ = [
+    "CairoPanic",
+    "ContractEnv",
+    "CoverageReport",
+    "Event",
+    "ExecutionContext",
+    "ExecutionResources",
+    "GasReport",
+    "GasSnapshot",
+    "L2ToL1Message",
+    "ProfileReport",
+    "Program",
+    "RunResult",
+    "accept_snapshots",
+    "pending_snapshots",
+    "reject_snapshots",
+    "run_scarb_pytest_py",
+]
@@ -0,0 +1,395 @@
+"""pytest plugin running the Cairo functions of a Scarb package as test items.
+
+Functions are collected from the Sierra artifact of every `Scarb.toml` found by pytest
+(`target/dev/<package>.sierra.json`, built with `scarb build`), and from Sierra artifacts passed
+explicitly on the command line. A function is collected if it takes no arguments and its path
+matches one of the `cairo_test_functions` patterns. It fails if it panics.
+
+What a Cairo function prints is shown in the report of its test if it fails. With
+`--cairo-stream-output`, it is also written to stdout as the function runs, e.g. with `-s`.
+
+With `--cairo-gas-snapshot`, the gas consumed by every Cairo call of the session is compared with
+the snapshot file at the end of the session, failing it on gas increases beyond
+`--cairo-gas-threshold` percent. `--cairo-update-gas-snapshot` records it instead.
+
+With `--cairo-coverage`, the Cairo source lines under the rootdir executed during the session are
+written to an LCOV file. This requires `unstable-add-statements-code-locations-debug-info = true`
+in the `[cairo]` section of `Scarb.toml`.
+
+With `--cairo-profile`, the VM steps executed per call stack during the session are written to a
+folded stacks file, which flamegraph tools such as `inferno-flamegraph` render, and the most
+expensive functions are listed in the terminal summary.
+
+The `cairo_snapshot` fixture compares the values returned by Cairo calls with snapshot files. A
+mismatching snapshot fails the test and its new version is written next to it as `<name>.snap.new`.
+Review the diff, then accept the new versions with `--cairo-accept-snapshots`, or update every
+snapshot while running with `--cairo-snapshot-update=always`.
+"""
+
+import fnmatch
+import itertools
+import re
+from pathlib import Path
+
+import pytest
+
+from .scarb_pytest import CairoPanic, GasSnapshot, Program, accept_snapshots
+
+DEFAULT_TEST_FUNCTIONS = ["test_*", "tests::*"]
+SIERRA_SUFFIX = ".sierra.json"
+
+_programs = {}
+_gas_report = None
+_coverage_report = None
+_profile_report = None
+_pending_snapshots = []
+
+
+def pytest_addoption(parser):
+    group = parser.getgroup("scarb-pytest")
+    group.addoption(
+        "--cairo-package",
+        dest="cairo_package",
+        help="Scarb package loaded by the `cairo_program` fixture.",
+    )
+    group.addoption(
+        "--cairo-sierra-path",
+        dest="cairo_sierra_path",
+        help="Sierra program loaded by the `cairo_program` fixture.",
+    )
+    group.addoption(
+        "--cairo-coverage",
+        dest="cairo_coverage",
+        help="LCOV file to write the Cairo source lines executed during the session to.",
+    )
+    group.addoption(
+        "--cairo-profile",
+        dest="cairo_profile",
+        help="Folded stacks file to write the VM steps executed per Cairo call stack to.",
+    )
+    group.addoption(
+        "--cairo-gas-snapshot",
+        dest="cairo_gas_snapshot",
+        help="Gas snapshot file to compare the gas consumed by Cairo calls against.",
+    )
+    group.addoption(
+        "--cairo-update-gas-snapshot",
+        action="store_true",
+        help="Record the gas consumed by Cairo calls in the gas snapshot file.",
+    )
+    group.addoption(
+        "--cairo-gas-threshold",
+        dest="cairo_gas_threshold",
+        help="Gas increase tolerated by the snapshot comparison, in percent.",
+    )
+    group.addoption(
+        "--cairo-gas-warn-only",
+        action="store_true",
+        help="Only report gas increases beyond the threshold, without failing the session.",
+    )
+    group.addoption(
+        "--cairo-stream-output",
+        action="store_true",
+        help="Write what Cairo functions print to stdout as they run.",
+    )
+    group.addoption(
+        "--cairo-snapshot-update",
+        dest="cairo_snapshot_update",
+        choices=["new", "always", "no"],
+        help="How mismatching Cairo snapshots are handled: write them as `.snap.new` files for "
+        "review (new, the default), overwrite them (always) or write nothing (no).",
+    )
+    group.addoption(
+        "--cairo-accept-snapshots",
+        action="store_true",
+        help="Accept the pending `.snap.new` Cairo snapshots under the rootdir before running.",
+    )
+    parser.addini(
+        "cairo_package", "Scarb package loaded by the `cairo_program` fixture."
+    )
+    parser.addini(
+        "cairo_sierra_path",
+        "Sierra program loaded by the `cairo_program` fixture, relative to the rootdir.",
+    )
+    parser.addini(
+        "cairo_gas_snapshot",
+        "Gas snapshot file to compare the gas consumed by Cairo calls against, relative to the "
+        "rootdir.",
+    )
+    parser.addini(
+        "cairo_gas_threshold",
+        "Gas increase tolerated by the snapshot comparison, in percent.",
+        default="0",
+    )
+    parser.addini(
+        "cairo_test_functions",
+        "Patterns of the Cairo functions collected as tests, matched against the end of their "
+        "path, e.g. `test_*` or `tests::*`.",
+        type="linelist",
+        default=DEFAULT_TEST_FUNCTIONS,
+    )
+
+
+def pytest_collect_file(file_path, parent):
+    if file_path.name == "Scarb.toml":
+        return CairoFile.from_parent(parent, path=file_path)
+    if file_path.name.endswith(SIERRA_SUFFIX) and _is_explicit_arg(parent.config, file_path):
+        return CairoFile.from_parent(parent, path=file_path)
+    return None
+
+
+@pytest.fixture(scope="session")
+def cairo_program(pytestconfig):
+    """The Cairo program configured with `cairo_sierra_path` or `cairo_package`."""
+    sierra_path = _option(pytestconfig, "cairo_sierra_path")
+    if sierra_path:
+        return _load_program(pytestconfig, pytestconfig.rootpath / sierra_path)
+    package = _option(pytestconfig, "cairo_package")
+    if package:
+        if package not in _programs:
+            _programs[package] = Program(package=package, **_instrumentation(pytestconfig))
+        return _programs[package]
+    raise pytest.UsageError(
+        "the `cairo_program` fixture requires the `cairo_sierra_path` or `cairo_package` option"
+    )
+
+
+@pytest.fixture
+def cairo_snapshot(request):
+    """Asserts that a Cairo call returns the value stored in a snapshot file.
+
+    `cairo_snapshot(function, arguments=None, *, program=None, name=None)` compares the value
+    returned by `function` with `snapshots/<test module>__<name>.snap` next to the test file.
+    `name` defaults to the test name, numbered after the first snapshot of the test, and `program`
+    to the `cairo_program` fixture.
+    """
+    counter = itertools.count()
+
+    def assert_snapshot(function, arguments=None, *, program=None, name=None):
+        if program is None:
+            program = request.getfixturevalue("cairo_program")
+        if name is None:
+            index = next(counter)
+            name = request.node.name if index == 0 else f"{request.node.name}-{index}"
+        name = re.sub(r"[^\w.-]", "_", name)
+        path = request.path.parent / "snapshots" / f"{request.path.stem}__{name}.snap"
+        try:
+            program.assert_snapshot(
+                path,
+                function,
+                arguments,
+                update=request.config.getoption("cairo_snapshot_update"),
+            )
+        except AssertionError:
+            pending = path.with_name(path.name + ".new")
+            if pending.exists():
+                _pending_snapshots.append(pending)
+            raise
+
+    return assert_snapshot
+
+
+def pytest_sessionstart(session):
+    if session.config.getoption("cairo_accept_snapshots"):
+        accept_snapshots(session.config.rootpath)
+
+
+def pytest_sessionfinish(session):
+    global _coverage_report, _profile_report
+    config = session.config
+    if config.getoption("cairo_coverage") is not None and _programs:
+        _coverage_report = None
+        for program in _programs.values():
+            report = program.coverage_report(source_root=config.rootpath)
+            if _coverage_report is None:
+                _coverage_report = report
+            else:
+                _coverage_report.merge(report)
+        _coverage_report.write_lcov(config.rootpath / config.getoption("cairo_coverage"))
+
+    if config.getoption("cairo_profile") is not None and _programs:
+        _profile_report = None
+        for program in _programs.values():
+            report = program.profile_report()
+            if _profile_report is None:
+                _profile_report = report
+            else:
+                _profile_report.merge(report)
+        _profile_report.write_folded(config.rootpath / config.getoption("cairo_profile"))
+
+    _check_gas_snapshot(session)
+
+
+def _check_gas_snapshot(session):
+    global _gas_report
+    config = session.config
+    path = _option(config, "cairo_gas_snapshot")
+    if not path:
+        return
+    path = config.rootpath / path
+
+    snapshot = GasSnapshot()
+    for program in _programs.values():
+        snapshot.merge(program.gas_snapshot())
+    baseline = GasSnapshot.load(path)
+    if config.getoption("cairo_update_gas_snapshot"):
+        baseline.merge(snapshot)
+        baseline.save(path)
+        return
+
+    _gas_report = snapshot.compare(baseline, float(_option(config, "cairo_gas_threshold")))
+    if _gas_report.has_regressions and not config.getoption("cairo_gas_warn_only"):
+        session.exitstatus = pytest.ExitCode.TESTS_FAILED
+
+
+def pytest_terminal_summary(terminalreporter):
+    if _coverage_report is not None:
+        terminalreporter.write_sep("=", "Cairo coverage")
+        terminalreporter.write_line(str(_coverage_report))
+    if _profile_report is not None:
+        terminalreporter.write_sep("=", "Cairo profile")
+        terminalreporter.write_line(str(_profile_report))
+    if _gas_report is not None:
+        terminalreporter.write_sep("=", "Cairo gas snapshot")
+        terminalreporter.write_line(str(_gas_report))
+    if _pending_snapshots:
+        terminalreporter.write_sep("=", "Cairo snapshots")
+        for path in _pending_snapshots:
+            terminalreporter.write_line(f"pending: {path}")
+        terminalreporter.write_line(
+            "review the changes, then accept them with --cairo-accept-snapshots"
+        )
+
+
+class CairoFile(pytest.File):
+    """A Scarb manifest or Sierra artifact, whose test functions are collected."""
+
+    def collect(self):
+        sierra_path = self._sierra_path()
+        if not sierra_path.is_file():
+            raise self.CollectError(f"Sierra program not found: {sierra_path}, run `scarb build`")
+        program = _load_program(self.config, sierra_path)
+
+        patterns = self.config.getini("cairo_test_functions")
+        functions = [
+            (function.split("::"), function)
+            for function, n_params in sorted(program.functions())
+            if n_params == 0 and _is_test_function(function, patterns)
+        ]
+        return _collect_functions(self, program, functions)
+
+    def _sierra_path(self):
+        if self.path.name != "Scarb.toml":
+            return self.path
+        package = _package_name(self.path)
+        if package is None:
+            raise self.CollectError(f"no package name in {self.path}")
+        return self.path.parent / "target" / "dev" / f"{package}{SIERRA_SUFFIX}"
+
+
+class CairoModule(pytest.Collector):
+    """A Cairo module, collected as one node per path segment so that node ids select tests like
+    `Scarb.toml::my_package::tests::test_foo`."""
+
+    def __init__(self, *, program, functions, **kwargs):
+        super().__init__(**kwargs)
+        self.program = program
+        self.functions = functions
+
+    def collect(self):
+        return _collect_functions(self, self.program, self.functions)
+
+
+class CairoItem(pytest.Item):
+    """A Cairo function run as a test."""
+
+    def __init__(self, *, program, function, **kwargs):
+        super().__init__(**kwargs)
+        self.program = program
+        self.function = function
+
+    def runtest(self):
+        result = self.program.call(self.function)
+        if result.output:
+            self.add_report_section("call", "cairo output", result.output)
+
+    def repr_failure(self, excinfo):
+        if isinstance(excinfo.value, CairoPanic):
+            # Includes what the function printed.
+            return f"{self.function} panicked: {excinfo.value}"
+        if isinstance(excinfo.value, RuntimeError):
+            return f"{self.function} failed: {excinfo.value}"
+        return super().repr_failure(excinfo)
+
+    def reportinfo(self):
+        return self.path, None, self.function
+
+
+def _collect_functions(parent, program, functions):
+    """Yields an item per function of `parent`, and a module per submodule.
+
+    `functions` are `(segments, function)` pairs, `segments` being the path of `function`
+    relative to `parent`.
+    """
+    modules = {}
+    for segments, function in functions:
+        if len(segments) == 1:
+            yield CairoItem.from_parent(
+                parent, name=segments[0], program=program, function=function
+            )
+        else:
+            modules.setdefault(segments[0], []).append((segments[1:], function))
+    for name, module_functions in modules.items():
+        yield CairoModule.from_parent(
+            parent, name=name, program=program, functions=module_functions
+        )
+
+
+def _load_program(config, sierra_path):
+    sierra_path = Path(sierra_path).resolve()
+    if sierra_path not in _programs:
+        _programs[sierra_path] = Program(sierra_path=sierra_path, **_instrumentation(config))
+    return _programs[sierra_path]
+
+
+def _instrumentation(config):
+    return {
+        "coverage": config.getoption("cairo_coverage") is not None,
+        "profile": config.getoption("cairo_profile") is not None,
+        "stream_output": config.getoption("cairo_stream_output"),
+    }
+
+
+def _is_test_function(function, patterns):
+    # Generated functions, e.g. closures (`foo[expr12]`) and generic impls (`Foo::<u8>::bar`),
+    # are never tests.
+    if "[" in function or "<" in function:
+        return False
+    segments = function.split("::")
+    suffixes = ["::".join(segments[i:]) for i in range(len(segments))]
+    return any(
+        fnmatch.fnmatchcase(suffix, pattern) for suffix in suffixes for pattern in patterns
+    )
+
+
+def _package_name(manifest_path):
+    in_package = False
+    for line in manifest_path.read_text().splitlines():
+        line = line.strip()
+        if line.startswith("["):
+            in_package = line == "[package]"
+        elif in_package:
+            match = re.match(r'name\s*=\s*"([^"]+)"', line)
+            if match:
+                return match.group(1)
+    return None
+
+
+def _option(config, name):
+    return config.getoption(name) or config.getini(name)
+
+
+def _is_explicit_arg(config, file_path):
+    return any(
+        Path(arg.split("::")[0]).resolve() == file_path.resolve() for arg in config.args
+    )
@@ -0,0 +1,99 @@
+"""Tests of the pytest plugin, running pytest sessions on a copy of the sample Cairo project."""
+
+import shutil
+from pathlib import Path
+
+import pytest
+
+CAIRO_PROJECT = Path(__file__).parents[2] / "cairo_project"
+DATA = Path(__file__).parent / "data"
+
+
+@pytest.fixture
+def sample_project(pytester):
+    """A copy of `cairo_project` in the pytester directory, built as by `scarb build`."""
+    shutil.copy(CAIRO_PROJECT / "Scarb.toml", pytester.path)
+    target = pytester.path / "target" / "dev"
+    target.mkdir(parents=True)
+    shutil.copy(CAIRO_PROJECT / "sample_project.sierra.json", target)
+    return pytester
+
+
+def test_collects_scarb_package(sample_project):
+    sample_project.makeini("[pytest]\ncairo_test_functions = main\n")
+    result = sample_project.runpytest("-v")
+    result.assert_outcomes(passed=1)
+    result.stdout.fnmatch_lines(["*Scarb.toml::sample_project::main PASSED*"])
+
+
+def test_skips_functions_with_parameters(sample_project):
+    # The tests of the sample project all take arguments.
+    sample_project.makeini("[pytest]\ncairo_test_functions = tests::*\n")
+    result = sample_project.runpytest()
+    assert result.ret == pytest.ExitCode.NO_TESTS_COLLECTED
+
+
+def test_panicking_function_fails(pytester):
+    shutil.copy(DATA / "print.sierra.json", pytester.path)
+    pytester.makeini("[pytest]\ncairo_test_functions = print_and_panic\n")
+    result = pytester.runpytest("print.sierra.json")
+    result.assert_outcomes(failed=1)
+    result.stdout.fnmatch_lines(
+        [
+            "*test::print_and_panic panicked: oops",
+            "*Cairo output:",
+            "*Hello!",
+        ]
+    )
+
+
+def test_cairo_program(sample_project):
+    sample_project.makeini(
+        "[pytest]\ncairo_sierra_path = target/dev/sample_project.sierra.json\n"
+    )
+    sample_project.makepyfile(
+        test_calls="""
+        def test_call(cairo_program):
+            assert cairo_program.call("extract_field_2", [[1, 2]]).values == [2]
+
+        def test_call_value(cairo_program):
+            # Sierra programs don't record member names, so structs decode as tuples.
+            assert cairo_program.call_value("my_struct", [1, 2]) == (1, 2)
+        """
+    )
+    result = sample_project.runpytest("test_calls.py")
+    result.assert_outcomes(passed=2)
+
+
+def test_cairo_snapshot(sample_project):
+    sample_project.makeini(
+        "[pytest]\ncairo_sierra_path = target/dev/sample_project.sierra.json\n"
+    )
+    sample_project.makepyfile(
+        test_snapshots="""
+        def test_my_struct(cairo_snapshot):
+            cairo_snapshot("my_struct", [1, 2])
+        """
+    )
+    snapshot = sample_project.path / "snapshots" / "test_snapshots__test_my_struct.snap"
+    pending = snapshot.with_name(snapshot.name + ".new")
+
+    # A missing snapshot fails the test, and is written for review.
+    result = sample_project.runpytest("test_snapshots.py", "--cairo-snapshot-update=new")
+    result.assert_outcomes(failed=1)
+    result.stdout.fnmatch_lines(["pending: *test_snapshots__test_my_struct.snap.new"])
+    assert pending.exists()
+    assert not snapshot.exists()
+
+    result = sample_project.runpytest(
+        "test_snapshots.py", "--cairo-snapshot-update=new", "--cairo-accept-snapshots"
+    )
+    result.assert_outcomes(passed=1)
+    assert not pending.exists()
+    assert "sample_project::utils::MyStruct(" in snapshot.read_text()
+
+    # A changed snapshot fails the test.
+    snapshot.write_text(snapshot.read_text().replace("    1,", "    3,"))
+    result = sample_project.runpytest("test_snapshots.py", "--cairo-snapshot-update=new")
+    result.assert_outcomes(failed=1)
+    assert pending.exists()